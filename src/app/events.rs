@@ -1,7 +1,10 @@
 use super::models::{InputMode, Page};
-use super::state::{load_dictionary, AppState, DownloadState};
+use super::state::{dict_files, load_dictionary, load_dictionary_info, AppState, DownloadState};
 use crate::config::{Config, DictConfig};
+use crate::dictionary::CacheManager;
 use crate::download::{download_and_install, fetch_available_dictionaries, find_dict_files};
+use crate::import::{build_reverse, import_file, import_id, ImportOptions};
+use anyhow::Context;
 use crossterm::event::{self, KeyCode, KeyModifiers};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
@@ -9,6 +12,7 @@ use std::thread;
 
 impl AppState {
     pub fn handle_translation_event(&mut self, key: event::KeyEvent) -> anyhow::Result<()> {
+        self.notice = None;
         match self.input_mode {
             InputMode::Normal => match key.code {
                 KeyCode::Char('q') => self.exit = true,
@@ -20,9 +24,25 @@ impl AppState {
                     self.input_mode = InputMode::Editing;
                 }
                 KeyCode::Esc => self.input_mode = InputMode::Editing,
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_fuzzy_search()
+                }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.cycle_search_mode()
+                }
+                KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_grouping()
+                }
+                KeyCode::Char('n') => self.next_link(),
+                KeyCode::Char('N') => self.previous_link(),
+                KeyCode::Enter => self.follow_link(),
+                KeyCode::Char('h') | KeyCode::Left => self.go_back(),
+                KeyCode::Char('l') | KeyCode::Right => self.go_forward(),
                 KeyCode::Tab => self.cycle_dictionary(),
                 KeyCode::Down => self.next_result(),
                 KeyCode::Up => self.previous_result(),
+                KeyCode::PageDown => self.next_page(),
+                KeyCode::PageUp => self.previous_page(),
                 _ => {}
             },
             InputMode::Editing => match key.code {
@@ -33,6 +53,15 @@ impl AppState {
                 KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.previous_result()
                 }
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_fuzzy_search()
+                }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.cycle_search_mode()
+                }
+                KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_grouping()
+                }
                 KeyCode::Char(c) if !c.is_numeric() || c == '0' => {
                     self.input.push(c);
                     self.perform_search();
@@ -45,13 +74,28 @@ impl AppState {
                 KeyCode::Tab => self.cycle_dictionary(),
                 KeyCode::Down => self.next_result(),
                 KeyCode::Up => self.previous_result(),
+                KeyCode::PageDown => self.next_page(),
+                KeyCode::PageUp => self.previous_page(),
                 _ => {}
             },
         }
         Ok(())
     }
 
+    // bounds checks stay inside the arms, a guard would let the key fall through
+    #[allow(clippy::collapsible_match)]
     pub fn handle_management_event(&mut self, key: event::KeyEvent) -> anyhow::Result<()> {
+        self.notice = None;
+        if self.import_input.is_some() {
+            self.handle_import_input(key);
+            return Ok(());
+        }
+        if let Some(path) = self.import_confirm.take() {
+            if key.code == KeyCode::Char('y') {
+                self.import_dictionary_file(path, true);
+            }
+            return Ok(());
+        }
         match key.code {
             KeyCode::Char('q') => self.exit = true,
             KeyCode::Esc => self.page = Page::Translation,
@@ -71,11 +115,126 @@ impl AppState {
             KeyCode::Char('d') => {
                 self.delete_selected_dictionary()?;
             }
+            KeyCode::Char('c') => self.clear_selected_cache(),
+            KeyCode::Char('i') => self.import_input = Some(String::new()),
+            KeyCode::Char('r') => self.build_selected_reverse(),
             _ => {}
         }
+        self.load_selected_info();
         Ok(())
     }
 
+    /// editing the path of a file to import
+    fn handle_import_input(&mut self, key: event::KeyEvent) {
+        let Some(input) = &mut self.import_input else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.import_input = None,
+            KeyCode::Enter => {
+                let path = input.trim().to_string();
+                self.import_input = None;
+                if path.is_empty() {
+                    return;
+                }
+                let path = match path.strip_prefix("~/").zip(dirs::home_dir()) {
+                    Some((rest, home)) => home.join(rest),
+                    None => PathBuf::from(path),
+                };
+                self.import_dictionary_file(path, false);
+            }
+            _ => {}
+        }
+    }
+
+    /// compiles a TEI file or TSV/CSV glossary with detected settings and loads it,
+    /// asks first when it would replace an earlier import
+    fn import_dictionary_file(&mut self, path: PathBuf, replace: bool) {
+        let options = ImportOptions {
+            replace,
+            ..ImportOptions::default()
+        };
+        let id = import_id(&path, &options);
+        let exists = self.config.dictionaries.iter().any(|d| d.id == id)
+            || Config::data_dir().is_ok_and(|data_dir| data_dir.join(&id).exists());
+        if exists && !replace {
+            self.import_confirm = Some(path);
+            return;
+        }
+
+        let label = format!("Importing {}", path.display());
+        self.start_building(label, move || {
+            let (dict_config, count) =
+                import_file(&path, &options, &Config::data_dir()?).context("Import failed")?;
+            let notice = format!("Imported {} entries as {}", count, dict_config.name);
+            Ok((dict_config, notice))
+        });
+    }
+
+    /// derives a dictionary in the opposite direction from the selected one
+    fn build_selected_reverse(&mut self) {
+        let Some(original) = self
+            .config
+            .dictionaries
+            .get(self.management_selected)
+            .cloned()
+        else {
+            return;
+        };
+
+        // an inactive dictionary is loaded just for reading its entries
+        let loaded = self.loaded_dictionaries.get(&original.id).cloned();
+        let label = format!("Building {} (reverse)", original.name);
+        self.start_building(label, move || {
+            let source = match loaded {
+                Some(source) => source,
+                None => Arc::from(load_dictionary(&original, None).context("Reverse failed")?),
+            };
+            let (dict_config, count) =
+                build_reverse(source.as_ref(), &original, &Config::data_dir()?)
+                    .context("Reverse failed")?;
+            let notice = format!("Built {} with {} entries", dict_config.name, count);
+            Ok((dict_config, notice))
+        });
+    }
+
+    /// adds an imported or derived dictionary to the config and loads it
+    pub(super) fn add_built_dictionary(&mut self, dict_config: DictConfig, notice: String) {
+        // building it again replaces the dictionary with the same id
+        self.loaded_dictionaries.remove(&dict_config.id);
+        self.dict_info.remove(&dict_config.id);
+        self.config.add_dictionary(dict_config.clone());
+        if let Err(e) = self.config.save() {
+            self.notice = Some(format!("Failed to save config: {}", e));
+            return;
+        }
+        self.management_selected = self.config.dictionaries.len() - 1;
+        self.notice = Some(notice);
+        self.start_loading(dict_config);
+    }
+
+    /// reads the metadata of the selected dictionary for the info panel
+    pub fn load_selected_info(&mut self) {
+        let Some(dict) = self.config.dictionaries.get(self.management_selected) else {
+            return;
+        };
+        if !self.dict_info.contains_key(&dict.id) {
+            // a loaded backend knows its own metadata
+            let info = match self.loaded_dictionaries.get(&dict.id) {
+                Some(source) => source.info(),
+                None => load_dictionary_info(dict),
+            }
+            .map_err(|e| format!("{:#}", e));
+            self.dict_info.insert(dict.id.clone(), info);
+        }
+    }
+
+    // bounds checks stay inside the arms, a guard would let the key fall through
+    #[allow(clippy::collapsible_match)]
     pub fn handle_download_event(&mut self, key: event::KeyEvent) -> anyhow::Result<()> {
         match self.download_input_mode {
             InputMode::Normal => match key.code {
@@ -163,20 +322,12 @@ impl AppState {
             // Load or unload dictionary
             if was_active {
                 self.loaded_dictionaries.remove(&dict_id);
+                self.dict_status.remove(&dict_id);
             } else if let Some(dict_config) =
                 self.config.dictionaries.iter().find(|d| d.id == dict_id)
             {
-                match load_dictionary(dict_config) {
-                    Ok(dict) => {
-                        self.loaded_dictionaries.insert(dict_id.clone(), dict);
-                    }
-                    Err(e) => {
-                        self.download_status = Some(format!("Failed to load dictionary: {}", e));
-                        // Deactivate it again
-                        self.config.toggle_dictionary(&dict_id);
-                        let _ = self.config.save();
-                    }
-                }
+                // a failed load deactivates it again
+                self.start_loading(dict_config.clone());
             }
 
             let active_count = self.loaded_dictionaries.len();
@@ -189,6 +340,29 @@ impl AppState {
         Ok(())
     }
 
+    /// removes the caches of the selected dictionary, an active one
+    /// is reloaded so they are rebuilt right away
+    fn clear_selected_cache(&mut self) {
+        let Some(dict) = self
+            .config
+            .dictionaries
+            .get(self.management_selected)
+            .cloned()
+        else {
+            return;
+        };
+        // drop the mapped caches before removing the files
+        let reload = self.loaded_dictionaries.remove(&dict.id).is_some();
+        let cleared = dict_files(&dict).and_then(|files| CacheManager::clear(&files.index));
+        self.notice = Some(match cleared {
+            Ok(removed) => format!("{}: removed {} cache file(s)", dict.name, removed),
+            Err(e) => format!("{}: failed to clear cache: {:#}", dict.name, e),
+        });
+        if reload {
+            self.start_loading(dict);
+        }
+    }
+
     fn delete_selected_dictionary(&mut self) -> anyhow::Result<()> {
         if let Some(dict) = self
             .config
@@ -202,7 +376,13 @@ impl AppState {
             let _ = self.config.save();
 
             self.loaded_dictionaries.remove(&dict_id);
+            self.dict_status.remove(&dict_id);
+            self.dict_info.remove(&dict_id);
 
+            // cache names depend on the resolved path, so clear them while it exists
+            if let Ok(files) = dict_files(&dict) {
+                let _ = CacheManager::clear(&files.index);
+            }
             let _ = std::fs::remove_dir_all(&dict.path);
 
             if self.management_selected >= self.config.dictionaries.len()
@@ -307,7 +487,7 @@ impl AppState {
 
     fn handle_download_success(&mut self, dict_name: String, dict_dir: PathBuf) {
         match find_dict_files(&dict_dir) {
-            Ok((index_path, _dict_path)) => {
+            Ok(files) => {
                 let dict_id = dict_name.clone();
 
                 // parse languages from dict_name
@@ -318,7 +498,7 @@ impl AppState {
                     ("UNK".to_string(), "UNK".to_string())
                 };
 
-                let dict_base_dir = index_path.parent().unwrap().to_path_buf();
+                let dict_base_dir = files.index.parent().unwrap().to_path_buf();
 
                 let dict_config = DictConfig {
                     id: dict_id.clone(),
@@ -327,23 +507,18 @@ impl AppState {
                     to_lang,
                     path: dict_base_dir,
                     active: true,
+                    strip_diacritics: true,
+                    format: files.format,
+                    origin: None,
                 };
 
+                self.dict_info.remove(&dict_id);
                 self.config.add_dictionary(dict_config.clone());
                 if let Err(e) = self.config.save() {
                     self.download_status = Some(format!("Failed to save config: {}", e));
                 } else {
-                    match load_dictionary(&dict_config) {
-                        Ok(dict) => {
-                            self.loaded_dictionaries.insert(dict_id, dict);
-                            self.download_status =
-                                Some(format!("Successfully installed {}", dict_name));
-                        }
-                        Err(e) => {
-                            self.download_status =
-                                Some(format!("Downloaded but failed to load: {}", e));
-                        }
-                    }
+                    self.start_loading(dict_config);
+                    self.download_status = Some(format!("Successfully installed {}", dict_name));
                 }
             }
             Err(e) => {
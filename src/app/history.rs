@@ -0,0 +1,114 @@
+use super::models::{HistoryEntry, SearchMode};
+use super::state::AppState;
+
+impl AppState {
+    /// cross-reference targets in the selected definition
+    pub fn current_links(&self) -> Vec<String> {
+        self.selected_entry
+            .as_ref()
+            .map(|entry| entry.parse().links)
+            .unwrap_or_default()
+    }
+
+    pub fn next_link(&mut self) {
+        let count = self.current_links().len();
+        if count > 0 {
+            self.selected_link = Some(self.selected_link.map_or(0, |i| (i + 1) % count));
+        }
+    }
+
+    pub fn previous_link(&mut self) {
+        let count = self.current_links().len();
+        if count > 0 {
+            self.selected_link = Some(
+                self.selected_link
+                    .map_or(count - 1, |i| (i + count - 1) % count),
+            );
+        }
+    }
+
+    /// looks up the selected link, the current search goes onto the back stack
+    pub fn follow_link(&mut self) {
+        let Some(target) = self
+            .selected_link
+            .and_then(|i| self.current_links().into_iter().nth(i))
+        else {
+            return;
+        };
+
+        let current = self.history_entry();
+        self.back_history.push(current);
+        self.forward_history.clear();
+
+        self.search_origin_dictionary();
+        self.input = target;
+        self.search_mode = SearchMode::Prefix;
+        self.perform_search();
+    }
+
+    /// links of a reverse dictionary point to headwords of the one it was
+    /// derived from, that one is searched instead
+    fn search_origin_dictionary(&mut self) {
+        let Some(origin) = self
+            .results
+            .get(self.selected_index)
+            .and_then(|item| {
+                self.config
+                    .dictionaries
+                    .iter()
+                    .find(|d| d.id == item.dict_id)
+            })
+            .and_then(|dict| dict.origin.as_ref())
+            .and_then(|origin| self.config.dictionaries.iter().find(|d| d.id == *origin))
+        else {
+            return;
+        };
+
+        match self
+            .ready_dictionaries()
+            .iter()
+            .position(|d| d.id == origin.id)
+        {
+            Some(i) => {
+                self.active_dict_index = i;
+                self.search_all = false;
+            }
+            None => self.notice = Some(format!("Activate {} to follow its links", origin.name)),
+        }
+    }
+
+    pub fn go_back(&mut self) {
+        if let Some(entry) = self.back_history.pop() {
+            let current = self.history_entry();
+            self.forward_history.push(current);
+            self.restore(entry);
+        }
+    }
+
+    pub fn go_forward(&mut self) {
+        if let Some(entry) = self.forward_history.pop() {
+            let current = self.history_entry();
+            self.back_history.push(current);
+            self.restore(entry);
+        }
+    }
+
+    fn history_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            query: self.input.clone(),
+            search_mode: self.search_mode,
+            dict_index: self.active_dict_index,
+            search_all: self.search_all,
+            selected_index: self.selected_index,
+        }
+    }
+
+    fn restore(&mut self, entry: HistoryEntry) {
+        self.input = entry.query;
+        self.search_mode = entry.search_mode;
+        self.active_dict_index = entry.dict_index;
+        self.search_all = entry.search_all;
+        self.perform_search();
+        self.restore_selection = entry.selected_index;
+    }
+}
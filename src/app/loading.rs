@@ -0,0 +1,123 @@
+use super::state::{load_dictionary, AppState, DictStatus};
+use crate::config::DictConfig;
+use anyhow::Result;
+use std::sync::Arc;
+use std::thread;
+
+impl AppState {
+    /// loads a dictionary on a worker thread, `check_dictionary_loads` picks it up
+    pub fn start_loading(&mut self, dict_config: DictConfig) {
+        self.dict_status
+            .insert(dict_config.id.clone(), DictStatus::Loading);
+
+        let sender = self.load_sender.clone();
+        let frequencies = self.config.frequency_list(&dict_config.from_lang);
+        thread::spawn(move || {
+            let result = load_dictionary(&dict_config, frequencies.as_deref())
+                .map_err(|e| format!("{:#}", e));
+            let _ = sender.send((dict_config.id, result));
+        });
+    }
+
+    pub fn check_dictionary_loads(&mut self) {
+        let mut changed = false;
+
+        while let Ok((dict_id, result)) = self.load_receiver.try_recv() {
+            // deactivated or deleted while it was loading
+            let Some(name) = self
+                .config
+                .dictionaries
+                .iter()
+                .find(|d| d.id == dict_id && d.active)
+                .map(|d| d.name.clone())
+            else {
+                self.dict_status.remove(&dict_id);
+                continue;
+            };
+
+            let current = self.active_dict_id();
+            match result {
+                Ok(dict) => {
+                    self.dict_status.remove(&dict_id);
+                    self.loaded_dictionaries.insert(dict_id, Arc::from(dict));
+                }
+                Err(e) => {
+                    self.notice = Some(format!("Failed to load {}: {}", name, e));
+                    self.config.toggle_dictionary(&dict_id);
+                    let _ = self.config.save();
+                    self.dict_status.insert(dict_id, DictStatus::Failed(e));
+                }
+            }
+
+            // keep searching the same dictionary when another one becomes ready
+            if let Some(current) = current {
+                if let Some(i) = self
+                    .ready_dictionaries()
+                    .iter()
+                    .position(|d| d.id == current)
+                {
+                    self.active_dict_index = i;
+                }
+            }
+            changed = true;
+        }
+
+        if changed {
+            self.perform_search();
+        }
+    }
+
+    /// imports or derives a dictionary on a worker thread, one at a time,
+    /// `check_builds` adds it once it's written
+    pub(super) fn start_building(
+        &mut self,
+        label: String,
+        build: impl FnOnce() -> Result<(DictConfig, String)> + Send + 'static,
+    ) {
+        if let Some(running) = &self.building {
+            self.notice = Some(format!("{}, wait for it to finish", running));
+            return;
+        }
+        self.building = Some(label);
+
+        let sender = self.build_sender.clone();
+        thread::spawn(move || {
+            let _ = sender.send(build().map_err(|e| format!("{:#}", e)));
+        });
+    }
+
+    pub fn check_builds(&mut self) {
+        while let Ok(result) = self.build_receiver.try_recv() {
+            self.building = None;
+            match result {
+                Ok((dict_config, notice)) => self.add_built_dictionary(dict_config, notice),
+                Err(e) => self.notice = Some(e),
+            }
+        }
+    }
+
+    /// active dictionaries that finished loading, in config order
+    pub fn ready_dictionaries(&self) -> Vec<&DictConfig> {
+        self.config
+            .get_active_dictionaries()
+            .into_iter()
+            .filter(|d| self.loaded_dictionaries.contains_key(&d.id))
+            .collect()
+    }
+
+    /// names of the dictionaries that are still loading
+    pub fn loading_dictionaries(&self) -> Vec<&str> {
+        self.config
+            .dictionaries
+            .iter()
+            .filter(|d| self.dict_status.get(&d.id) == Some(&DictStatus::Loading))
+            .map(|d| d.name.as_str())
+            .collect()
+    }
+
+    pub(super) fn active_dict_id(&self) -> Option<String> {
+        self.ready_dictionaries()
+            .get(self.active_dict_index)
+            .map(|d| d.id.clone())
+    }
+}
@@ -1,10 +1,13 @@
 mod events;
+mod history;
+mod loading;
 mod models;
 mod search;
 mod state;
+mod worker;
 
-pub use models::{InputMode, Page};
-pub use state::AppState;
+pub use models::{InputMode, Page, SearchMode};
+pub use state::{AppState, DictStatus};
 
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
 
@@ -34,23 +37,28 @@ impl App {
     /// handle input
     pub fn handle_event(&mut self) -> anyhow::Result<()> {
         self.state.check_download_progress();
+        self.state.check_dictionary_loads();
+        self.state.check_builds();
+        self.state.check_search_results();
 
         if let Event::Key(key) = event::read()? {
-            // global keys
+            // global keys, except while typing a path
+            let typing_path = self.state.import_input.is_some();
             match key.code {
                 KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.state.exit = true;
                     return Ok(());
                 }
-                KeyCode::Char('1') => {
+                KeyCode::Char('1') if !typing_path => {
                     self.state.page = Page::Translation;
                     return Ok(());
                 }
-                KeyCode::Char('2') => {
+                KeyCode::Char('2') if !typing_path => {
                     self.state.page = Page::Management;
+                    self.state.load_selected_info();
                     return Ok(());
                 }
-                KeyCode::Char('3') => {
+                KeyCode::Char('3') if !typing_path => {
                     self.state.page = Page::Download;
                     if self.state.available_dicts.is_none() && !self.state.loading_dicts {
                         self.state.fetch_available_dictionaries();
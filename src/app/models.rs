@@ -1,3 +1,5 @@
+use crate::dictionary::Hit;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Page {
     Translation,
@@ -5,8 +7,72 @@ pub enum Page {
     Download,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchMode {
+    Prefix,
+    Fuzzy,
+    /// headwords ending with the query
+    Suffix,
+    /// headwords containing the query
+    Infix,
+    /// reverse search through the definition text
+    Definitions,
+}
+
+impl SearchMode {
+    pub fn toggle_fuzzy(self) -> Self {
+        match self {
+            SearchMode::Fuzzy => SearchMode::Prefix,
+            _ => SearchMode::Fuzzy,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            SearchMode::Prefix => SearchMode::Fuzzy,
+            SearchMode::Fuzzy => SearchMode::Suffix,
+            SearchMode::Suffix => SearchMode::Infix,
+            SearchMode::Infix => SearchMode::Definitions,
+            SearchMode::Definitions => SearchMode::Prefix,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchMode::Prefix => "prefix",
+            SearchMode::Fuzzy => "fuzzy",
+            SearchMode::Suffix => "suffix",
+            SearchMode::Infix => "infix",
+            SearchMode::Definitions => "definitions",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputMode {
     Normal,
     Editing,
 }
+
+/// a search the user can go back or forward to
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub search_mode: SearchMode,
+    pub dict_index: usize,
+    pub search_all: bool,
+    pub selected_index: usize,
+}
+
+/// a row of the results list, its definition is read once it's selected
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultItem {
+    pub hit: Hit,
+    /// the dictionary that found it
+    pub dict_id: String,
+    /// dictionary name shown in merged searches
+    pub source: Option<String>,
+}
+
+/// results that haven't been shown yet, read a page at a time
+pub type ResultCursor = Box<dyn Iterator<Item = ResultItem> + Send>;
@@ -1,53 +1,184 @@
 use super::state::AppState;
+use super::worker::{SearchRequest, SearchResponse, Target, PAGE_SIZE};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// the next page is loaded when the selection gets this close to the end
+const PAGE_MARGIN: usize = 10;
+/// searches finishing sooner don't show the spinner, so typing doesn't flicker
+const SPINNER_DELAY: Duration = Duration::from_millis(150);
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
 
 impl AppState {
+    /// searches the input on the worker thread, the results stay until
+    /// `check_search_results` picks up the new ones
     pub fn perform_search(&mut self) {
-        if self.loaded_dictionaries.is_empty() {
-            self.results = Vec::new();
+        self.restore_selection = 0;
+
+        // dictionaries that are still loading are skipped until they are ready
+        let ready = self.ready_dictionaries().len();
+        if self.active_dict_index >= ready {
+            self.active_dict_index = 0;
+        }
+        if ready < 2 {
+            self.search_all = false;
+        }
+
+        let targets: Vec<Target> = self
+            .ready_dictionaries()
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| self.search_all || *i == self.active_dict_index)
+            .filter_map(|(_, d)| {
+                let dict = self.loaded_dictionaries.get(&d.id)?;
+                Some((d.id.clone(), d.name.clone(), Arc::clone(dict)))
+            })
+            .collect();
+
+        if targets.is_empty() {
+            let generation = self.search_worker.cancel();
+            self.show_results(SearchResponse::empty(generation));
             return;
         }
 
-        let active_configs: Vec<_> = self.config.get_active_dictionaries();
-        if active_configs.is_empty() {
-            self.results = Vec::new();
+        self.search_worker.submit(SearchRequest {
+            targets,
+            query: self.input.clone(),
+            mode: self.search_mode,
+            labels: self.search_all,
+            group: self.group_results,
+        });
+        self.search_started.get_or_insert_with(Instant::now);
+    }
+
+    /// shows the pages of the latest search the worker sent so far
+    pub fn check_search_results(&mut self) {
+        for response in self.search_worker.try_recv() {
+            self.show_results(response);
+        }
+    }
+
+    /// the first page replaces the results, later ones are appended
+    fn show_results(&mut self, response: SearchResponse) {
+        if response.offset == 0 {
+            self.search_started = None;
+            self.fuzzy_fallback = response.fuzzy_fallback;
+            self.search_error = response.error;
+            self.results = response.results;
+        } else if response.offset == self.results.len() {
+            self.results.extend(response.results);
+        } else {
             return;
         }
+        self.more_results = response.more;
+        self.loading_more = false;
 
-        if self.active_dict_index >= active_configs.len() {
-            self.active_dict_index = 0;
+        // going back selects a result that may be a few pages down
+        if response.offset == 0 || self.restore_selection > self.selected_index {
+            self.select_result(self.restore_selection);
+        }
+        if self.selected_index >= self.restore_selection || !self.more_results {
+            self.restore_selection = 0;
         }
+        self.collect_notices();
+    }
+
+    /// animation frame while a slow search is running
+    pub fn search_spinner(&self) -> Option<char> {
+        let elapsed = self.search_started?.elapsed();
+        if elapsed < SPINNER_DELAY {
+            return None;
+        }
+        let frame = (elapsed.as_millis() / 100) as usize % SPINNER_FRAMES.len();
+        Some(SPINNER_FRAMES[frame])
+    }
+
+    /// more pages are loaded when scrolling down
+    pub fn has_more_results(&self) -> bool {
+        self.more_results
+    }
 
-        if let Some(dict_config) = active_configs.get(self.active_dict_index) {
-            if let Some(dict) = self.loaded_dictionaries.get(&dict_config.id) {
-                self.results = dict.lookup(&self.input);
-                self.selected_index = 0;
-            }
+    /// selects a result and reads its definition, scrolling close to the end
+    /// of the loaded results asks the worker for the next page
+    pub(super) fn select_result(&mut self, index: usize) {
+        if index + PAGE_MARGIN >= self.results.len() && self.more_results && !self.loading_more {
+            self.loading_more = true;
+            self.search_worker.more();
         }
+        self.selected_index = index.min(self.results.len().saturating_sub(1));
+        self.selected_link = None;
+        self.selected_entry = self.results.get(self.selected_index).and_then(|item| {
+            self.loaded_dictionaries
+                .get(&item.dict_id)?
+                .entry(&item.hit)
+        });
     }
 
+    /// the next dictionary, with several of them "All" comes after the last one
     pub fn cycle_dictionary(&mut self) {
-        let active_count = self.config.get_active_dictionaries().len();
-        if active_count > 0 {
+        let active_count = self.ready_dictionaries().len();
+        if active_count == 0 {
+            return;
+        }
+
+        if self.search_all {
+            self.search_all = false;
+            self.active_dict_index = 0;
+        } else if self.active_dict_index + 1 >= active_count && active_count > 1 {
+            self.search_all = true;
+        } else {
             self.active_dict_index = (self.active_dict_index + 1) % active_count;
+        }
+        self.perform_search();
+    }
+
+    /// merged results either interleaved by relevance or grouped by dictionary
+    pub fn toggle_grouping(&mut self) {
+        self.group_results = !self.group_results;
+        if self.search_all {
             self.perform_search();
         }
     }
 
+    pub fn toggle_fuzzy_search(&mut self) {
+        self.search_mode = self.search_mode.toggle_fuzzy();
+        self.perform_search();
+    }
+
+    pub fn cycle_search_mode(&mut self) {
+        self.search_mode = self.search_mode.next();
+        self.perform_search();
+    }
+
     pub fn next_result(&mut self) {
-        if !self.results.is_empty() && self.selected_index < self.results.len() - 1 {
-            self.selected_index += 1;
+        if self.selected_index + 1 < self.results.len() {
+            self.select_result(self.selected_index + 1);
         }
     }
 
     pub fn previous_result(&mut self) {
         if self.selected_index > 0 {
-            self.selected_index -= 1;
+            self.select_result(self.selected_index - 1);
+        }
+    }
+
+    pub fn next_page(&mut self) {
+        if self.selected_index + 1 < self.results.len() {
+            self.select_result(self.selected_index + PAGE_SIZE);
+        }
+    }
+
+    pub fn previous_page(&mut self) {
+        if self.selected_index > 0 {
+            self.select_result(self.selected_index.saturating_sub(PAGE_SIZE));
         }
     }
 
     pub fn get_active_dict_name(&self) -> String {
-        let active_configs: Vec<_> = self.config.get_active_dictionaries();
-        if let Some(dict) = active_configs.get(self.active_dict_index) {
+        let active_configs = self.ready_dictionaries();
+        if self.search_all {
+            format!("All ({})", active_configs.len())
+        } else if let Some(dict) = active_configs.get(self.active_dict_index) {
             format!("{} -> {}", dict.from_lang, dict.to_lang)
         } else {
             "No active dictionary".to_string()
@@ -1,10 +1,15 @@
-use super::models::{InputMode, Page};
+use super::models::{HistoryEntry, InputMode, Page, ResultItem, SearchMode};
+use super::worker::SearchWorker;
 use crate::config::{Config, DictConfig};
-use crate::dictionary::{DictEntry, Dictionary};
+use crate::dictionary::{
+    DictEntry, DictFiles, Dictionary, DictionaryInfo, DictionarySource, Lemmatizer, Normalizer,
+};
 use crate::download::FreeDictEntry;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 #[derive(Debug)]
 pub struct DownloadState {
@@ -12,23 +17,77 @@ pub struct DownloadState {
     pub result: Option<Result<(String, PathBuf), String>>,
 }
 
+/// dictionaries that are not in `loaded_dictionaries` yet
+#[derive(Debug, Clone, PartialEq)]
+pub enum DictStatus {
+    Loading,
+    Failed(String),
+}
+
+/// dictionary id and the outcome of loading it on a worker thread
+pub(super) type LoadResult = (String, Result<Box<dyn DictionarySource>, String>);
+
+/// config of an imported or derived dictionary and the notice to show, or the error
+pub(super) type BuildResult = Result<(DictConfig, String), String>;
+
 pub struct AppState {
     pub page: Page,
     pub exit: bool,
 
     // dictionary data
     pub config: Config,
-    pub loaded_dictionaries: HashMap<String, Dictionary>,
+    /// shared with the search worker
+    pub loaded_dictionaries: HashMap<String, Arc<dyn DictionarySource>>,
+    pub dict_status: HashMap<String, DictStatus>,
+    pub(super) load_sender: Sender<LoadResult>,
+    pub(super) load_receiver: Receiver<LoadResult>,
+    /// e.g. "Importing words.csv" while a dictionary is built on a worker thread
+    pub building: Option<String>,
+    pub(super) build_sender: Sender<BuildResult>,
+    pub(super) build_receiver: Receiver<BuildResult>,
 
     // translation page
     pub input: String,
     pub input_mode: InputMode,
-    pub results: Vec<DictEntry>,
+    pub search_mode: SearchMode,
+    /// prefix search found nothing and the results come from fuzzy search
+    pub fuzzy_fallback: bool,
+    /// e.g. an incomplete regular expression
+    pub search_error: Option<String>,
+    /// e.g. a cache that was rebuilt, cleared on the next key press
+    pub notice: Option<String>,
+    /// the pages of results loaded so far
+    pub results: Vec<ResultItem>,
+    /// the worker has another page of results
+    pub(super) more_results: bool,
+    /// the next page was asked for and hasn't arrived yet
+    pub(super) loading_more: bool,
     pub selected_index: usize,
+    /// definition of the selected result, read when it gets selected
+    pub selected_entry: Option<DictEntry>,
+    /// result to select once the running search is done, e.g. when going back
+    pub(super) restore_selection: usize,
+    pub(super) search_worker: SearchWorker,
+    /// when the running search was started, None when there is none
+    pub(super) search_started: Option<Instant>,
     pub active_dict_index: usize,
+    /// search every ready dictionary instead of the active one
+    pub search_all: bool,
+    /// merged results are listed per dictionary instead of by relevance
+    pub group_results: bool,
+    /// index into the links of the selected definition
+    pub selected_link: Option<usize>,
+    pub back_history: Vec<HistoryEntry>,
+    pub forward_history: Vec<HistoryEntry>,
 
     // management page
     pub management_selected: usize,
+    /// metadata of the dictionaries selected so far, read on demand
+    pub dict_info: HashMap<String, Result<DictionaryInfo, String>>,
+    /// path typed after pressing `i`, None when not importing
+    pub import_input: Option<String>,
+    /// file whose earlier import would be replaced, waiting for `y`
+    pub import_confirm: Option<PathBuf>,
 
     // download page state
     pub available_dicts: Option<Vec<FreeDictEntry>>,
@@ -43,46 +102,45 @@ pub struct AppState {
 
 impl AppState {
     pub fn new() -> anyhow::Result<Self> {
-        let mut config = Config::load()?;
-
-        let mut loaded_dictionaries = HashMap::new();
-        let mut failed_dict_ids = Vec::new();
-
-        for dict_config in config.get_active_dictionaries() {
-            match load_dictionary(dict_config) {
-                Ok(dict) => {
-                    loaded_dictionaries.insert(dict_config.id.clone(), dict);
-                }
-                Err(e) => {
-                    eprintln!(
-                        "Warning: Failed to load dictionary {}: {}",
-                        dict_config.name, e
-                    );
-                    failed_dict_ids.push(dict_config.id.clone());
-                }
-            }
-        }
+        let config = Config::load()?;
+        let (load_sender, load_receiver) = mpsc::channel();
+        let (build_sender, build_receiver) = mpsc::channel();
 
-        // deactivate failed dictionaries
-        for dict_id in failed_dict_ids {
-            if let Some(d) = config.dictionaries.iter_mut().find(|d| d.id == dict_id) {
-                d.active = false;
-            }
-        }
-
-        let _ = config.save();
-
-        Ok(Self {
+        let mut state = Self {
             page: Page::Translation,
             exit: false,
             input: String::new(),
             input_mode: InputMode::Editing,
+            search_mode: SearchMode::Prefix,
+            fuzzy_fallback: false,
+            search_error: None,
+            notice: None,
             results: Vec::new(),
+            more_results: false,
+            loading_more: false,
             selected_index: 0,
+            selected_entry: None,
+            restore_selection: 0,
+            search_worker: SearchWorker::spawn(),
+            search_started: None,
             active_dict_index: 0,
+            search_all: false,
+            group_results: false,
+            selected_link: None,
+            back_history: Vec::new(),
+            forward_history: Vec::new(),
             config,
-            loaded_dictionaries,
+            loaded_dictionaries: HashMap::new(),
+            dict_status: HashMap::new(),
+            load_sender,
+            load_receiver,
+            building: None,
+            build_sender,
+            build_receiver,
             management_selected: 0,
+            dict_info: HashMap::new(),
+            import_input: None,
+            import_confirm: None,
             available_dicts: None,
             download_selected: 0,
             download_filter: String::new(),
@@ -91,13 +149,63 @@ impl AppState {
             loading_dicts: false,
             download_progress: None,
             download_state: None,
-        })
+        };
+        let active: Vec<DictConfig> = state
+            .config
+            .get_active_dictionaries()
+            .into_iter()
+            .cloned()
+            .collect();
+        for dict_config in active {
+            state.start_loading(dict_config);
+        }
+        Ok(state)
+    }
+
+    /// shows caches the loaded dictionaries had to rebuild
+    pub fn collect_notices(&mut self) {
+        let notices: Vec<String> = self
+            .config
+            .dictionaries
+            .iter()
+            .filter_map(|d| Some((d, self.loaded_dictionaries.get(&d.id)?)))
+            .flat_map(|(d, dict)| {
+                dict.take_notices()
+                    .into_iter()
+                    .map(move |notice| format!("{}: {}", d.name, notice))
+            })
+            .collect();
+
+        if !notices.is_empty() {
+            self.notice = Some(notices.join(" | "));
+        }
     }
 }
 
-pub fn load_dictionary(dict_config: &DictConfig) -> anyhow::Result<Dictionary> {
-    let index_path = dict_config.path.join(format!("{}.index", dict_config.id));
-    let dict_path = dict_config.path.join(format!("{}.dict.dz", dict_config.id));
+/// `frequencies` is the list that ranks prefix matches, see `Config::frequency_list`
+pub fn load_dictionary(
+    dict_config: &DictConfig,
+    frequencies: Option<&Path>,
+) -> anyhow::Result<Box<dyn DictionarySource>> {
+    let files = dict_files(dict_config)?;
+
+    let normalizer = Normalizer::for_language(&dict_config.from_lang, dict_config.strip_diacritics);
+    let text_normalizer =
+        Normalizer::for_language(&dict_config.to_lang, dict_config.strip_diacritics);
+
+    let lemmatizer = Lemmatizer::for_language(&dict_config.from_lang);
+
+    let dictionary = Dictionary::new(&files, normalizer, text_normalizer, lemmatizer, frequencies)?;
+    Ok(Box::new(dictionary))
+}
+
+pub fn load_dictionary_info(dict_config: &DictConfig) -> anyhow::Result<DictionaryInfo> {
+    DictionaryInfo::load(&dict_files(dict_config)?)
+}
 
-    Dictionary::new(&index_path, &dict_path)
+/// files in the configured format, or whatever the directory contains
+pub fn dict_files(dict_config: &DictConfig) -> anyhow::Result<DictFiles> {
+    DictFiles::locate(&dict_config.path, dict_config.format)
+        .or_else(|| DictFiles::detect(&dict_config.path))
+        .ok_or_else(|| anyhow::anyhow!("No dictionary files found in {:?}", dict_config.path))
 }
@@ -0,0 +1,345 @@
+use super::models::{ResultCursor, ResultItem, SearchMode};
+use crate::dictionary::{Cancel, DictionarySource, Hits, Pattern};
+use std::iter::{self, Peekable};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// keystrokes arriving within this time are searched as one query
+const DEBOUNCE: Duration = Duration::from_millis(50);
+/// results sent at a time, the next page is sent when the UI asks for it
+pub(super) const PAGE_SIZE: usize = 50;
+
+/// dictionary id, the name shown in merged results and the backend
+pub(super) type Target = (String, String, Arc<dyn DictionarySource>);
+
+/// everything the worker needs for one search
+pub(super) struct SearchRequest {
+    pub targets: Vec<Target>,
+    pub query: String,
+    pub mode: SearchMode,
+    /// label the results with their dictionary
+    pub labels: bool,
+    /// list merged results per dictionary instead of by relevance
+    pub group: bool,
+}
+
+enum Job {
+    Search(u64, SearchRequest),
+    /// the next page of the search with this generation
+    More(u64),
+}
+
+/// one page of results
+pub(super) struct SearchResponse {
+    pub generation: u64,
+    /// results sent before this page, 0 for the first page of a search
+    pub offset: usize,
+    pub results: Vec<ResultItem>,
+    /// the worker has another page
+    pub more: bool,
+    /// prefix search found nothing and the results come from fuzzy search
+    pub fuzzy_fallback: bool,
+    /// e.g. an incomplete regular expression
+    pub error: Option<String>,
+}
+
+impl SearchResponse {
+    pub fn empty(generation: u64) -> Self {
+        Self {
+            generation,
+            offset: 0,
+            results: Vec::new(),
+            more: false,
+            fuzzy_fallback: false,
+            error: None,
+        }
+    }
+}
+
+/// searches on a background thread, a newer query cancels the older ones
+pub(super) struct SearchWorker {
+    sender: Sender<Job>,
+    receiver: Receiver<SearchResponse>,
+    /// generation of the latest query, results of older ones are dropped
+    generation: Arc<AtomicU64>,
+}
+
+impl SearchWorker {
+    pub fn spawn() -> Self {
+        let (sender, requests) = mpsc::channel();
+        let (responses, receiver) = mpsc::channel();
+        let generation = Arc::new(AtomicU64::new(0));
+
+        let latest = Arc::clone(&generation);
+        thread::spawn(move || run(requests, responses, latest));
+
+        Self {
+            sender,
+            receiver,
+            generation,
+        }
+    }
+
+    pub fn submit(&self, request: SearchRequest) {
+        let generation = self.cancel();
+        let _ = self.sender.send(Job::Search(generation, request));
+    }
+
+    /// asks for the next page of the latest search
+    pub fn more(&self) {
+        let generation = self.generation.load(Ordering::SeqCst);
+        let _ = self.sender.send(Job::More(generation));
+    }
+
+    /// makes the searches in flight stale, returns the next generation
+    pub fn cancel(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// the pages of the latest query that arrived so far, in order
+    pub fn try_recv(&self) -> Vec<SearchResponse> {
+        let latest = self.generation.load(Ordering::SeqCst);
+        self.receiver
+            .try_iter()
+            .filter(|response| response.generation == latest)
+            .collect()
+    }
+}
+
+/// the results of the latest search, read a page at a time
+struct Cursor {
+    generation: u64,
+    results: Peekable<ResultCursor>,
+    /// results sent so far
+    sent: usize,
+    fuzzy_fallback: bool,
+    error: Option<String>,
+}
+
+impl Cursor {
+    fn page(&mut self) -> SearchResponse {
+        let results: Vec<ResultItem> = self.results.by_ref().take(PAGE_SIZE).collect();
+        let offset = self.sent;
+        self.sent += results.len();
+        SearchResponse {
+            generation: self.generation,
+            offset,
+            results,
+            more: self.results.peek().is_some(),
+            fuzzy_fallback: self.fuzzy_fallback,
+            error: self.error.clone(),
+        }
+    }
+}
+
+fn run(jobs: Receiver<Job>, responses: Sender<SearchResponse>, latest: Arc<AtomicU64>) {
+    let mut cursor: Option<Cursor> = None;
+
+    while let Ok(job) = jobs.recv() {
+        let mut next = match job {
+            Job::Search(generation, request) => (generation, request),
+            Job::More(generation) => {
+                let current = cursor.as_mut().filter(|c| c.generation == generation);
+                if let Some(cursor) = current {
+                    if responses.send(cursor.page()).is_err() {
+                        return;
+                    }
+                }
+                continue;
+            }
+        };
+
+        // wait for typing to pause, only the last query is searched
+        loop {
+            match jobs.recv_timeout(DEBOUNCE) {
+                Ok(Job::Search(generation, request)) => next = (generation, request),
+                // pages of results the new query replaces
+                Ok(Job::More(_)) => {}
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        let (generation, request) = next;
+        let cancel = Cancel::new(Arc::clone(&latest), generation);
+        cursor = None;
+        if cancel.is_cancelled() {
+            continue;
+        }
+        let Some(mut results) = search(generation, request, &cancel) else {
+            continue;
+        };
+        // the first page goes out right away, later ones when asked for
+        let page = results.page();
+        if cancel.is_cancelled() {
+            continue;
+        }
+        if responses.send(page).is_err() {
+            return;
+        }
+        cursor = Some(results);
+    }
+}
+
+/// None when a newer query arrived before the fuzzy fallback
+fn search(generation: u64, request: SearchRequest, cancel: &Cancel) -> Option<Cursor> {
+    let SearchRequest {
+        targets,
+        query,
+        mode,
+        labels,
+        group,
+    } = request;
+
+    // wildcard and regex syntax only applies to headword searches
+    let pattern = match mode {
+        SearchMode::Prefix | SearchMode::Fuzzy => Pattern::parse(&query),
+        _ => None,
+    };
+    let pattern = match pattern {
+        Some(Err(e)) => {
+            return Some(Cursor {
+                generation,
+                results: (Box::new(iter::empty()) as ResultCursor).peekable(),
+                sent: 0,
+                fuzzy_fallback: false,
+                error: Some(format!("{:#}", e)),
+            });
+        }
+        pattern => pattern.and_then(Result::ok),
+    };
+
+    let hits = search_each(&targets, |dict| {
+        search_dictionary(dict, pattern.as_ref(), mode, &query, cancel)
+    });
+    let error = hits
+        .iter()
+        .all(Option::is_none)
+        .then(|| format!("This dictionary does not support {} search", mode.label()));
+    let mut results = merge_results(&targets, hits, labels, group).peekable();
+
+    let mut fuzzy_fallback = false;
+    if pattern.is_none() && mode == SearchMode::Prefix && results.peek().is_none() {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        let hits = search_each(&targets, |dict| dict.lookup_fuzzy(&query, cancel));
+        results = merge_results(&targets, hits, labels, group).peekable();
+        fuzzy_fallback = results.peek().is_some();
+    }
+
+    Some(Cursor {
+        generation,
+        results,
+        sent: 0,
+        fuzzy_fallback,
+        error,
+    })
+}
+
+fn search_dictionary(
+    dict: &dyn DictionarySource,
+    pattern: Option<&Pattern>,
+    mode: SearchMode,
+    query: &str,
+    cancel: &Cancel,
+) -> Option<Hits> {
+    match (pattern, mode) {
+        (Some(pattern), _) => dict.lookup_pattern(pattern, cancel),
+        (None, SearchMode::Prefix) => Some(dict.lookup(query, cancel)),
+        (None, SearchMode::Fuzzy) => dict.lookup_fuzzy(query, cancel),
+        (None, SearchMode::Suffix) => dict.lookup_suffix(query, cancel),
+        (None, SearchMode::Infix) => dict.lookup_infix(query, cancel),
+        (None, SearchMode::Definitions) => dict.lookup_definitions(query, cancel),
+    }
+}
+
+/// runs `search` on every dictionary, in parallel when there are several,
+/// None for the ones that don't support it
+fn search_each(
+    targets: &[Target],
+    search: impl Fn(&dyn DictionarySource) -> Option<Hits> + Sync,
+) -> Vec<Option<Hits>> {
+    if let [(_, _, dict)] = targets {
+        return vec![search(dict.as_ref())];
+    }
+
+    let search = &search;
+    thread::scope(|scope| {
+        let handles: Vec<_> = targets
+            .iter()
+            .map(|(_, _, dict)| scope.spawn(move || search(dict.as_ref())))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or(None))
+            .collect()
+    })
+}
+
+fn merge_results(
+    targets: &[Target],
+    hits: Vec<Option<Hits>>,
+    labels: bool,
+    group: bool,
+) -> ResultCursor {
+    let sources = targets
+        .iter()
+        .zip(hits)
+        .filter_map(|((dict_id, name, _), hits)| {
+            Some(Source {
+                dict_id: dict_id.clone(),
+                label: labels.then(|| name.clone()),
+                hits: hits?.peekable(),
+                taken: 0,
+            })
+        })
+        .collect();
+
+    Box::new(MergedResults { sources, group })
+}
+
+struct Source {
+    dict_id: String,
+    label: Option<String>,
+    hits: Peekable<Hits>,
+    /// hits handed out so far, the rank of the next one
+    taken: usize,
+}
+
+/// the hits of every searched dictionary as result rows, interleaved with
+/// exact headword matches first and then by rank within their dictionary,
+/// or one dictionary after another when grouped
+struct MergedResults {
+    sources: Vec<Source>,
+    group: bool,
+}
+
+impl Iterator for MergedResults {
+    type Item = ResultItem;
+
+    fn next(&mut self) -> Option<ResultItem> {
+        let group = self.group;
+        let (_, i) = self
+            .sources
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, source)| {
+                let hit = source.hits.peek()?;
+                let relevance = (!group).then_some((!hit.exact, source.taken));
+                Some((relevance, i))
+            })
+            .min()?;
+
+        let source = &mut self.sources[i];
+        source.taken += 1;
+        Some(ResultItem {
+            hit: source.hits.next()?,
+            dict_id: source.dict_id.clone(),
+            source: source.label.clone(),
+        })
+    }
+}
@@ -1,5 +1,7 @@
+use crate::dictionary::DictFormat;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
@@ -11,11 +13,41 @@ pub struct DictConfig {
     pub to_lang: String,
     pub path: PathBuf,
     pub active: bool,
+    /// fold accented letters to their base letter when searching
+    #[serde(default = "default_strip_diacritics")]
+    pub strip_diacritics: bool,
+    /// configs written before StarDict support are dictd
+    #[serde(default)]
+    pub format: DictFormat,
+    /// id of the dictionary a reverse dictionary was derived from, its
+    /// links point to headwords there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
+
+fn default_strip_diacritics() -> bool {
+    true
+}
+
+/// how prefix matches after the exact one are ordered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ranking {
+    /// shorter headwords first, then alphabetical
+    Length,
+    /// common words first when the language has a frequency list, otherwise by length
+    #[default]
+    Frequency,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
     pub dictionaries: Vec<DictConfig>,
+    #[serde(default)]
+    pub ranking: Ranking,
+    /// `word count` lists by language code, e.g. "eng" -> "~/en_50k.txt"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub frequency_lists: HashMap<String, PathBuf>,
 }
 
 impl Config {
@@ -25,6 +57,8 @@ impl Config {
         if !config_path.exists() {
             let config = Self {
                 dictionaries: Vec::new(),
+                ranking: Ranking::default(),
+                frequency_lists: HashMap::new(),
             };
             config.save()?;
             return Ok(config);
@@ -62,6 +96,33 @@ impl Config {
         Ok(dict_dir)
     }
 
+    /// the frequency list used to rank headwords in `lang`, either configured
+    /// or `frequency/<lang>.txt` in the data directory
+    pub fn frequency_list(&self, lang: &str) -> Option<PathBuf> {
+        if self.ranking != Ranking::Frequency {
+            return None;
+        }
+
+        let lang = lang.to_lowercase();
+        let configured = self
+            .frequency_lists
+            .iter()
+            .find(|(code, _)| code.to_lowercase() == lang)
+            .map(
+                |(_, path)| match path.strip_prefix("~").ok().zip(dirs::home_dir()) {
+                    Some((rest, home)) => home.join(rest),
+                    None => path.clone(),
+                },
+            );
+        configured.or_else(|| {
+            let path = dirs::data_dir()?
+                .join("tuidict")
+                .join("frequency")
+                .join(format!("{}.txt", lang));
+            path.exists().then_some(path)
+        })
+    }
+
     pub fn add_dictionary(&mut self, dict: DictConfig) {
         self.dictionaries.retain(|d| d.id != dict.id);
         self.dictionaries.push(dict);
@@ -0,0 +1,240 @@
+use super::cancel::Cancel;
+use super::index::HeadwordIndex;
+use super::table::{read_u32, SortedTable, TableBytes};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+const SUFFIX_MAGIC: &[u8; 4] = b"TDSX";
+const INFIX_MAGIC: &[u8; 4] = b"TDNG";
+const VERSION: u32 = 1;
+
+/// n-gram length of the infix index, shorter queries scan every key
+const GRAM_LEN: usize = 3;
+
+/// reversed headword keys, so suffix queries become prefix queries
+///
+/// each key is a reversed folded headword key, the payload is its record u32
+pub struct SuffixIndex {
+    table: SortedTable,
+}
+
+/// trigrams of every headword key pointing at the records containing them
+///
+/// each key is a trigram, the payload lists record u32 in ascending order
+pub struct InfixIndex {
+    table: SortedTable,
+}
+
+impl SuffixIndex {
+    pub fn load(bytes: TableBytes, base: usize, headwords: &HeadwordIndex) -> Result<Self> {
+        let table = SortedTable::load(bytes, base, SUFFIX_MAGIC, VERSION, headwords.tag())?;
+        Ok(Self { table })
+    }
+
+    #[cfg(test)]
+    pub fn from_bytes(bytes: Vec<u8>, headwords: &HeadwordIndex) -> Result<Self> {
+        Self::load(TableBytes::Owned(bytes), 0, headwords)
+    }
+
+    pub fn build(headwords: &HeadwordIndex) -> Vec<u8> {
+        let mut reversed: Vec<(Vec<u8>, [u8; 4])> = (0..headwords.len())
+            .map(|record| {
+                let key: String = headwords.key(record).chars().rev().collect();
+                (key.into_bytes(), (record as u32).to_le_bytes())
+            })
+            .collect();
+        reversed.sort_unstable();
+
+        SortedTable::encode(
+            reversed
+                .iter()
+                .map(|(key, record)| (key.as_slice(), &record[..])),
+            SUFFIX_MAGIC,
+            VERSION,
+            headwords.tag(),
+        )
+    }
+
+    /// records whose key ends with the folded `suffix`
+    pub fn search(&self, suffix: &str) -> Vec<usize> {
+        let reversed: String = suffix.chars().rev().collect();
+        self.table
+            .prefix_range(reversed.as_bytes())
+            .filter(|&i| self.table.payload(i).len() == 4)
+            .map(|i| read_u32(self.table.payload(i), 0) as usize)
+            .collect()
+    }
+}
+
+impl InfixIndex {
+    pub fn load(bytes: TableBytes, base: usize, headwords: &HeadwordIndex) -> Result<Self> {
+        let table = SortedTable::load(bytes, base, INFIX_MAGIC, VERSION, headwords.tag())?;
+        Ok(Self { table })
+    }
+
+    #[cfg(test)]
+    pub fn from_bytes(bytes: Vec<u8>, headwords: &HeadwordIndex) -> Result<Self> {
+        Self::load(TableBytes::Owned(bytes), 0, headwords)
+    }
+
+    pub fn build(headwords: &HeadwordIndex) -> Vec<u8> {
+        let mut grams: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for record in 0..headwords.len() {
+            let key: Vec<char> = headwords.key(record).chars().collect();
+            let mut seen: Vec<String> = key.windows(GRAM_LEN).map(String::from_iter).collect();
+            seen.sort_unstable();
+            seen.dedup();
+
+            // records are visited in order, so every posting list stays sorted
+            for gram in seen {
+                grams
+                    .entry(gram)
+                    .or_default()
+                    .extend_from_slice(&(record as u32).to_le_bytes());
+            }
+        }
+
+        SortedTable::encode(
+            grams
+                .iter()
+                .map(|(gram, records)| (gram.as_bytes(), records.as_slice())),
+            INFIX_MAGIC,
+            VERSION,
+            headwords.tag(),
+        )
+    }
+
+    /// records whose key contains the folded `infix`
+    pub fn search(
+        &self,
+        infix: &str,
+        headwords: &HeadwordIndex,
+        cancel: &Cancel,
+    ) -> Result<Vec<usize>> {
+        let chars: Vec<char> = infix.chars().collect();
+        if chars.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // too short for a trigram, every key is scanned
+        if chars.len() < GRAM_LEN {
+            let mut records = Vec::new();
+            for record in 0..headwords.len() {
+                cancel.check(record)?;
+                if headwords.key(record).contains(infix) {
+                    records.push(record);
+                }
+            }
+            return Ok(records);
+        }
+
+        let mut candidates: Option<Vec<u32>> = None;
+        for gram in chars.windows(GRAM_LEN) {
+            let gram = String::from_iter(gram);
+            let start = self.table.lower_bound(gram.as_bytes());
+            let postings: Vec<u32> =
+                if start < self.table.len() && self.table.key(start) == gram.as_bytes() {
+                    self.table
+                        .payload(start)
+                        .chunks_exact(4)
+                        .map(|record| read_u32(record, 0))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+
+            candidates = Some(match candidates {
+                None => postings,
+                Some(current) => intersect(&current, &postings),
+            });
+            if candidates.as_ref().is_some_and(|c| c.is_empty()) {
+                return Ok(Vec::new());
+            }
+        }
+
+        // trigrams can appear in the wrong order, so verify the candidates
+        Ok(candidates
+            .unwrap_or_default()
+            .into_iter()
+            .map(|record| record as usize)
+            .filter(|&record| headwords.key(record).contains(infix))
+            .collect())
+    }
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::new();
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                result.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::normalize::Normalizer;
+    use crate::dictionary::trie::PrefixTrie;
+
+    fn headwords() -> HeadwordIndex {
+        let mut trie = PrefixTrie::new();
+        for (i, word) in [
+            "Möglichkeit",
+            "Freiheit",
+            "Keit",
+            "Graph",
+            "Photograph",
+            "Paragraf",
+        ]
+        .iter()
+        .enumerate()
+        {
+            trie.insert(word, i as u64, 1);
+        }
+        HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), Normalizer::default()).unwrap()
+    }
+
+    fn keys(headwords: &HeadwordIndex, mut records: Vec<usize>) -> Vec<String> {
+        records.sort_unstable();
+        records.into_iter().map(|r| headwords.key(r)).collect()
+    }
+
+    #[test]
+    fn test_suffix_search() {
+        let headwords = headwords();
+        let suffix = SuffixIndex::from_bytes(SuffixIndex::build(&headwords), &headwords).unwrap();
+
+        assert_eq!(
+            keys(&headwords, suffix.search("keit")),
+            vec!["keit", "moglichkeit"]
+        );
+        assert_eq!(keys(&headwords, suffix.search("heit")), vec!["freiheit"]);
+    }
+
+    #[test]
+    fn test_infix_search() {
+        let headwords = headwords();
+        let cancel = Cancel::default();
+        let infix = InfixIndex::from_bytes(InfixIndex::build(&headwords), &headwords).unwrap();
+        let search = |infix_query| infix.search(infix_query, &headwords, &cancel).unwrap();
+
+        assert_eq!(
+            keys(&headwords, search("graph")),
+            vec!["graph", "photograph"]
+        );
+        assert_eq!(
+            keys(&headwords, search("ei")),
+            vec!["freiheit", "keit", "moglichkeit"]
+        );
+        assert!(search("xyz").is_empty());
+    }
+}
@@ -1,83 +1,360 @@
+use super::affix::{InfixIndex, SuffixIndex};
+use super::files::{DictFiles, DictFormat};
+use super::fulltext::FullTextIndex;
+use super::index::HeadwordIndex;
+use super::info::is_metadata_headword;
+use super::normalize::Normalizer;
+use super::stardict;
+use super::table::{read_u32, read_u64, SettingsMismatch, TableBytes};
 use super::trie::PrefixTrie;
 use anyhow::{Context, Result};
+use memmap2::Mmap;
+use std::fmt;
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-/// stores trie structure + dictionary
+const CACHE_MAGIC: &[u8; 4] = b"TDCF";
+/// bump when the header or any table layout changes
+const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// magic | format version u32 | source count u32 | reserved u32
+const FIXED_HEADER_LEN: usize = 16;
+/// size u64 | crc32 u32 | reserved u32 per source file
+const SOURCE_STAMP_LEN: usize = 16;
+/// payload length u64 | crc32 u32 | reserved u32
+const PAYLOAD_INFO_LEN: usize = 16;
+
+/// stores the headword index and the secondary indexes built from it
 pub struct CacheManager;
 
+/// why an existing cache file was thrown away and rebuilt
+#[derive(Debug, Clone, PartialEq)]
+pub enum RebuildReason {
+    UnknownFormat,
+    FormatVersion {
+        found: u32,
+        expected: u32,
+    },
+    SourceChanged,
+    Corrupt,
+    /// the table was built with other settings, e.g. normalization rules
+    Settings,
+}
+
+impl fmt::Display for RebuildReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RebuildReason::UnknownFormat => write!(f, "cache was written by an older version"),
+            RebuildReason::FormatVersion { found, expected } => {
+                write!(f, "cache format {} does not match {}", found, expected)
+            }
+            RebuildReason::SourceChanged => write!(f, "dictionary files changed"),
+            RebuildReason::Corrupt => write!(f, "cache file was corrupt or truncated"),
+            RebuildReason::Settings => write!(f, "search settings changed"),
+        }
+    }
+}
+
 impl CacheManager {
-    fn cache_path(source_path: &Path) -> std::path::PathBuf {
+    /// `$XDG_CACHE_HOME/tuidict` on linux, so read-only dictionary
+    /// directories can be cached too
+    pub fn cache_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("tuidict")
+    }
+
+    /// `foo.index` -> `<cache dir>/foo.index-<path hash>.<suffix>`
+    ///
+    /// the hash keeps dictionaries with the same file name apart, a collision
+    /// is caught by the source stamps in the header and only costs a rebuild
+    fn cache_path(cache_dir: &Path, source_path: &Path, suffix: &str) -> PathBuf {
+        cache_dir.join(format!("{}.{}", Self::cache_stem(source_path), suffix))
+    }
+
+    fn cache_stem(source_path: &Path) -> String {
+        let absolute = fs::canonicalize(source_path).unwrap_or_else(|_| source_path.to_path_buf());
+        let name = source_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        format!(
+            "{}-{:08x}",
+            name,
+            crc32fast::hash(absolute.as_os_str().as_encoded_bytes())
+        )
+    }
+
+    /// older versions wrote `foo.index.<suffix>` next to the source
+    fn legacy_cache_path(source_path: &Path, suffix: &str) -> PathBuf {
         let mut path = source_path.to_path_buf();
         let mut extension = path
             .extension()
             .and_then(|s| s.to_str())
             .unwrap_or("")
             .to_string();
-        extension.push_str(".cache");
+        extension.push('.');
+        extension.push_str(suffix);
         path.set_extension(extension);
         path
     }
 
-    fn is_cache_valid(cache_path: &Path, source_path: &Path) -> bool {
-        if !cache_path.exists() {
-            return false;
+    /// removes every cache built from `index_path`, returns how many files were removed
+    pub fn clear(index_path: &Path) -> Result<usize> {
+        Self::clear_in(&Self::cache_dir(), index_path)
+    }
+
+    fn clear_in(cache_dir: &Path, index_path: &Path) -> Result<usize> {
+        let prefix = format!("{}.", Self::cache_stem(index_path));
+        Self::remove_cache_files(cache_dir, |name| name.starts_with(&prefix))
+    }
+
+    /// removes the caches of all dictionaries
+    pub fn clear_all() -> Result<usize> {
+        Self::remove_cache_files(&Self::cache_dir(), |_| true)
+    }
+
+    fn remove_cache_files(dir: &Path, matches: impl Fn(&str) -> bool) -> Result<usize> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read cache directory {:?}", dir))
+            }
+        };
+
+        let mut removed = 0;
+        for entry in entries {
+            let entry = entry?;
+            if matches(&entry.file_name().to_string_lossy()) {
+                fs::remove_file(entry.path())
+                    .with_context(|| format!("Failed to remove {:?}", entry.path()))?;
+                removed += 1;
+            }
         }
+        Ok(removed)
+    }
+
+    pub fn load_or_build_index(
+        files: &DictFiles,
+        normalizer: Normalizer,
+    ) -> Result<(HeadwordIndex, Option<RebuildReason>)> {
+        Self::load_or_build_index_in(&Self::cache_dir(), files, normalizer)
+    }
+
+    fn load_or_build_index_in(
+        cache_dir: &Path,
+        files: &DictFiles,
+        normalizer: Normalizer,
+    ) -> Result<(HeadwordIndex, Option<RebuildReason>)> {
+        Self::load_or_build(
+            &Self::cache_path(cache_dir, &files.index, "cache"),
+            &files.index_sources(),
+            |bytes, base| HeadwordIndex::load(bytes, base, normalizer),
+            || {
+                let trie = match files.format {
+                    DictFormat::Dictd => Self::build_trie_from_index(&files.index, normalizer)?,
+                    DictFormat::StarDict => stardict::build_trie(files, normalizer)?,
+                };
+                Ok(HeadwordIndex::encode(&trie))
+            },
+        )
+    }
+
+    /// the full-text index is built on first use since it reads every definition
+    pub fn load_or_build_fulltext(
+        files: &DictFiles,
+        headwords: &HeadwordIndex,
+        body: impl Fn(u64, u64) -> Option<String>,
+        normalizer: Normalizer,
+    ) -> Result<(FullTextIndex, Option<RebuildReason>)> {
+        let mut sources = files.index_sources();
+        sources.push(&files.data);
+
+        Self::load_or_build(
+            &Self::cache_path(&Self::cache_dir(), &files.index, "fulltext.cache"),
+            &sources,
+            |bytes, base| FullTextIndex::load(bytes, base, headwords, normalizer),
+            || Ok(FullTextIndex::build(headwords, body, normalizer)),
+        )
+    }
+
+    pub fn load_or_build_suffix(
+        files: &DictFiles,
+        headwords: &HeadwordIndex,
+    ) -> Result<(SuffixIndex, Option<RebuildReason>)> {
+        Self::load_or_build(
+            &Self::cache_path(&Self::cache_dir(), &files.index, "suffix.cache"),
+            &files.index_sources(),
+            |bytes, base| SuffixIndex::load(bytes, base, headwords),
+            || Ok(SuffixIndex::build(headwords)),
+        )
+    }
+
+    pub fn load_or_build_infix(
+        files: &DictFiles,
+        headwords: &HeadwordIndex,
+    ) -> Result<(InfixIndex, Option<RebuildReason>)> {
+        Self::load_or_build(
+            &Self::cache_path(&Self::cache_dir(), &files.index, "ngram.cache"),
+            &files.index_sources(),
+            |bytes, base| InfixIndex::load(bytes, base, headwords),
+            || Ok(InfixIndex::build(headwords)),
+        )
+    }
+
+    /// maps a valid cache file, or builds the table and writes it to the cache
+    ///
+    /// the reason is None when the cache was used or did not exist yet
+    fn load_or_build<T>(
+        cache_path: &Path,
+        sources: &[&Path],
+        load: impl Fn(TableBytes, usize) -> Result<T>,
+        build: impl FnOnce() -> Result<Vec<u8>>,
+    ) -> Result<(T, Option<RebuildReason>)> {
+        let reason = match Self::map_cache(cache_path, sources) {
+            Ok(None) => None,
+            Ok(Some((mmap, base))) => match load(TableBytes::Mapped(mmap), base) {
+                Ok(table) => return Ok((table, None)),
+                Err(e) if e.is::<SettingsMismatch>() => Some(RebuildReason::Settings),
+                Err(_) => Some(RebuildReason::Corrupt),
+            },
+            Err(reason) => Some(reason),
+        };
 
-        match (fs::metadata(cache_path), fs::metadata(source_path)) {
-            (Ok(cache_meta), Ok(source_meta)) => {
-                if let (Ok(cache_time), Ok(source_time)) =
-                    (cache_meta.modified(), source_meta.modified())
-                {
-                    cache_time >= source_time
-                } else {
-                    false
+        let stamps = sources
+            .iter()
+            .map(|source| Self::stamp(source))
+            .collect::<Result<Vec<_>>>()?;
+        let payload = build()?;
+
+        // map the written cache so the pages are shared with other instances
+        if Self::save_cache(cache_path, &stamps, &payload).is_ok() {
+            if let Ok(Some((mmap, base))) = Self::map_cache(cache_path, sources) {
+                if let Ok(table) = load(TableBytes::Mapped(mmap), base) {
+                    return Ok((table, reason));
                 }
             }
-            _ => false,
         }
+
+        Ok((load(TableBytes::Owned(payload), 0)?, reason))
     }
 
-    pub fn load_or_build_trie(index_path: &Path) -> Result<PrefixTrie> {
-        let cache_path = Self::cache_path(index_path);
+    /// maps the cache file and checks its header against the sources
+    ///
+    /// sources are only hashed when their mtime is newer than the cache,
+    /// so an unchanged dictionary costs a checksum of the payload only
+    fn map_cache(
+        cache_path: &Path,
+        sources: &[&Path],
+    ) -> std::result::Result<Option<(Mmap, usize)>, RebuildReason> {
+        let Ok(file) = File::open(cache_path) else {
+            return Ok(None);
+        };
+        // SAFETY: cache files are only ever replaced, never modified in place
+        let mmap = unsafe { Mmap::map(&file) }.map_err(|_| RebuildReason::Corrupt)?;
+
+        let header_len = FIXED_HEADER_LEN + sources.len() * SOURCE_STAMP_LEN + PAYLOAD_INFO_LEN;
+        if mmap.len() < FIXED_HEADER_LEN || &mmap[0..4] != CACHE_MAGIC {
+            return Err(RebuildReason::UnknownFormat);
+        }
+        let version = read_u32(&mmap, 4);
+        if version != CACHE_FORMAT_VERSION {
+            return Err(RebuildReason::FormatVersion {
+                found: version,
+                expected: CACHE_FORMAT_VERSION,
+            });
+        }
+        if read_u32(&mmap, 8) as usize != sources.len() || mmap.len() < header_len {
+            return Err(RebuildReason::Corrupt);
+        }
+
+        let cache_modified = file.metadata().and_then(|m| m.modified()).ok();
+        for (i, source) in sources.iter().enumerate() {
+            let at = FIXED_HEADER_LEN + i * SOURCE_STAMP_LEN;
+            let (size, hash) = (read_u64(&mmap, at), read_u32(&mmap, at + 8));
 
-        if Self::is_cache_valid(&cache_path, index_path) {
-            if let Ok(trie) = Self::load_trie_from_cache(&cache_path) {
-                return Ok(trie);
+            let meta = fs::metadata(source).map_err(|_| RebuildReason::SourceChanged)?;
+            if meta.len() != size {
+                return Err(RebuildReason::SourceChanged);
+            }
+
+            let touched = match (meta.modified().ok(), cache_modified) {
+                (Some(source_time), Some(cache_time)) => source_time > cache_time,
+                _ => true,
+            };
+            if touched {
+                let current = Self::hash_file(source).map_err(|_| RebuildReason::SourceChanged)?;
+                if current != hash {
+                    return Err(RebuildReason::SourceChanged);
+                }
+                // same content, skip hashing on the next start
+                let _ = file.set_modified(SystemTime::now());
             }
         }
 
-        let trie = Self::build_trie_from_index(index_path)?;
-        let _ = Self::save_trie_to_cache(&trie, &cache_path);
-        Ok(trie)
+        let info = header_len - PAYLOAD_INFO_LEN;
+        let (payload_len, checksum) = (read_u64(&mmap, info), read_u32(&mmap, info + 8));
+        if (mmap.len() - header_len) as u64 != payload_len {
+            return Err(RebuildReason::Corrupt);
+        }
+        if crc32fast::hash(&mmap[header_len..]) != checksum {
+            return Err(RebuildReason::Corrupt);
+        }
+
+        Ok(Some((mmap, header_len)))
     }
 
-    pub fn load_or_decompress_dict(dict_path: &Path) -> Result<String> {
-        let cache_path = Self::cache_path(dict_path);
+    /// size and content hash of a source file
+    fn stamp(source: &Path) -> Result<(u64, u32)> {
+        let size = fs::metadata(source)
+            .with_context(|| format!("Failed to read metadata of {:?}", source))?
+            .len();
+        Ok((size, Self::hash_file(source)?))
+    }
 
-        if Self::is_cache_valid(&cache_path, dict_path) {
-            if let Ok(content) = Self::load_dict_from_cache(&cache_path) {
-                return Ok(content);
+    fn hash_file(path: &Path) -> Result<u32> {
+        let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
             }
+            hasher.update(&buffer[..read]);
         }
+        Ok(hasher.finalize())
+    }
 
-        let content = Self::decompress_dict(dict_path)?;
-        let _ = Self::save_dict_to_cache(&content, &cache_path);
-        Ok(content)
+    /// older versions kept their caches next to the dictionary files,
+    /// including a fully decompressed copy of the .dict.dz
+    pub fn remove_legacy_caches(index_path: &Path, dict_path: &Path) {
+        let legacy = ["cache", "fulltext.cache", "suffix.cache", "ngram.cache"]
+            .iter()
+            .map(|suffix| Self::legacy_cache_path(index_path, suffix))
+            .chain([Self::legacy_cache_path(dict_path, "cache")]);
+        for path in legacy {
+            if path.exists() {
+                let _ = fs::remove_file(path);
+            }
+        }
     }
 
-    fn build_trie_from_index(index_path: &Path) -> Result<PrefixTrie> {
+    fn build_trie_from_index(index_path: &Path, normalizer: Normalizer) -> Result<PrefixTrie> {
         let index_file = File::open(index_path)
             .with_context(|| format!("Failed to open index file: {:?}", index_path))?;
         let reader = BufReader::new(index_file);
-        let mut trie = PrefixTrie::new();
+        let mut trie = PrefixTrie::with_normalizer(normalizer);
 
         for line in reader.lines() {
             let line = line?;
             let parts: Vec<&str> = line.trim().split('\t').collect();
 
-            if parts.len() >= 3 {
+            // 00-database-* entries are shown as dictionary info instead
+            if parts.len() >= 3 && !is_metadata_headword(parts[0]) {
                 let word = parts[0];
                 let offset = Self::decode_dict_number(parts[1])?;
                 let length = Self::decode_dict_number(parts[2])?;
@@ -88,50 +365,53 @@ impl CacheManager {
         Ok(trie)
     }
 
-    fn decompress_dict(dict_path: &Path) -> Result<String> {
-        let dict_file = File::open(dict_path)
-            .with_context(|| format!("Failed to open dict file: {:?}", dict_path))?;
-        let mut decoder = flate2::read::GzDecoder::new(dict_file);
-        let mut content = String::new();
-        decoder
-            .read_to_string(&mut content)
-            .context("Failed to decompress dictionary data")?;
-        Ok(content)
-    }
-
-    fn save_trie_to_cache(trie: &PrefixTrie, cache_path: &Path) -> Result<()> {
-        let encoded = bincode::serialize(trie).context("Failed to serialize trie")?;
-        let mut file = File::create(cache_path)
-            .with_context(|| format!("Failed to create cache file: {:?}", cache_path))?;
-        file.write_all(&encoded)
-            .context("Failed to write cache file")?;
-        Ok(())
-    }
-
-    fn load_trie_from_cache(cache_path: &Path) -> Result<PrefixTrie> {
-        let mut file = File::open(cache_path)
-            .with_context(|| format!("Failed to open cache file: {:?}", cache_path))?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)
-            .context("Failed to read cache file")?;
-        let trie = bincode::deserialize(&buffer).context("Failed to deserialize trie")?;
-        Ok(trie)
-    }
+    /// header with the source stamps and payload checksum, then the table,
+    /// written to a temp file and renamed over the old cache
+    fn save_cache(cache_path: &Path, stamps: &[(u64, u32)], payload: &[u8]) -> Result<()> {
+        let mut header = Vec::with_capacity(
+            FIXED_HEADER_LEN + stamps.len() * SOURCE_STAMP_LEN + PAYLOAD_INFO_LEN,
+        );
+        header.extend_from_slice(CACHE_MAGIC);
+        header.extend_from_slice(&CACHE_FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&(stamps.len() as u32).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        for (size, hash) in stamps {
+            header.extend_from_slice(&size.to_le_bytes());
+            header.extend_from_slice(&hash.to_le_bytes());
+            header.extend_from_slice(&0u32.to_le_bytes());
+        }
+        header.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        header.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
 
-    fn save_dict_to_cache(content: &str, cache_path: &Path) -> Result<()> {
-        let mut file = File::create(cache_path)
-            .with_context(|| format!("Failed to create cache file: {:?}", cache_path))?;
-        file.write_all(content.as_bytes())
-            .context("Failed to write cache file")?;
-        Ok(())
-    }
+        let dir = cache_path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cache directory {:?}", dir))?;
 
-    fn load_dict_from_cache(cache_path: &Path) -> Result<String> {
-        fs::read_to_string(cache_path)
-            .with_context(|| format!("Failed to read cache file: {:?}", cache_path))
+        // a crash mid-write must never leave a truncated file under the real name
+        let mut temp_name = cache_path.as_os_str().to_owned();
+        temp_name.push(format!(".{}.tmp", std::process::id()));
+        let temp_path = PathBuf::from(temp_name);
+
+        let written = File::create(&temp_path)
+            .with_context(|| format!("Failed to create cache file: {:?}", temp_path))
+            .and_then(|mut file| {
+                file.write_all(&header)?;
+                file.write_all(payload)?;
+                file.sync_all()?;
+                Ok(())
+            })
+            .and_then(|_| {
+                fs::rename(&temp_path, cache_path)
+                    .with_context(|| format!("Failed to replace cache file: {:?}", cache_path))
+            });
+        if written.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        written
     }
 
-    fn decode_dict_number(b64_str: &str) -> Result<u64> {
+    pub(super) fn decode_dict_number(b64_str: &str) -> Result<u64> {
         const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
         let mut result: u64 = 0;
@@ -147,3 +427,68 @@ impl CacheManager {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_in(cache_dir: &Path, index_path: &Path) -> Option<RebuildReason> {
+        let files = DictFiles {
+            format: DictFormat::Dictd,
+            index: index_path.to_path_buf(),
+            data: index_path.with_extension("dict.dz"),
+            ifo: None,
+            synonyms: None,
+        };
+        let (index, reason) =
+            CacheManager::load_or_build_index_in(cache_dir, &files, Normalizer::default()).unwrap();
+        assert!(!index.search_prefix("haus", 10).is_empty());
+        reason
+    }
+
+    #[test]
+    fn test_rebuilds_stale_or_damaged_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        let index_path = dir.path().join("test.index");
+        let cache_path = CacheManager::cache_path(&cache_dir, &index_path, "cache");
+        let load = |index_path: &Path| load_in(&cache_dir, index_path);
+        fs::write(&index_path, "Haus\tA\tK\nHund\tK\tK\n").unwrap();
+
+        assert_eq!(load(&index_path), None);
+        assert_eq!(load(&index_path), None);
+
+        let mut bytes = fs::read(&cache_path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&cache_path, &bytes).unwrap();
+        assert_eq!(load(&index_path), Some(RebuildReason::Corrupt));
+
+        fs::write(&cache_path, &bytes[..20]).unwrap();
+        assert_eq!(load(&index_path), Some(RebuildReason::Corrupt));
+
+        // a damaged table header is not mistaken for changed settings, even
+        // when the checksum was updated with it
+        bytes = fs::read(&cache_path).unwrap();
+        let header_len = FIXED_HEADER_LEN + SOURCE_STAMP_LEN + PAYLOAD_INFO_LEN;
+        bytes[header_len] ^= 0xff;
+        let checksum = crc32fast::hash(&bytes[header_len..]);
+        bytes[header_len - 8..header_len - 4].copy_from_slice(&checksum.to_le_bytes());
+        fs::write(&cache_path, &bytes).unwrap();
+        assert_eq!(load(&index_path), Some(RebuildReason::Corrupt));
+
+        fs::write(&index_path, "Haus\tA\tK\nHund\tK\tK\nKatze\tU\tK\n").unwrap();
+        assert_eq!(load(&index_path), Some(RebuildReason::SourceChanged));
+
+        bytes = fs::read(&cache_path).unwrap();
+        bytes[4] = 0;
+        fs::write(&cache_path, &bytes).unwrap();
+        assert!(matches!(
+            load(&index_path),
+            Some(RebuildReason::FormatVersion { .. })
+        ));
+
+        assert_eq!(CacheManager::clear_in(&cache_dir, &index_path).unwrap(), 1);
+        assert!(!cache_path.exists());
+    }
+}
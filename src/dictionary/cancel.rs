@@ -0,0 +1,70 @@
+use anyhow::{bail, Result};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// keys or entries visited between two looks at the latest generation
+const CHECK_EVERY: usize = 1024;
+
+/// stops a long search or index build once a newer query was typed
+///
+/// the default token is never cancelled
+#[derive(Clone, Default)]
+pub struct Cancel {
+    latest: Option<Arc<AtomicU64>>,
+    generation: u64,
+}
+
+/// the search was given up for a newer one, not worth a notice
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Search was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl Cancel {
+    /// cancelled as soon as `latest` moves past `generation`
+    pub fn new(latest: Arc<AtomicU64>, generation: u64) -> Self {
+        Self {
+            latest: Some(latest),
+            generation,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.latest
+            .as_ref()
+            .is_some_and(|latest| latest.load(Ordering::SeqCst) != self.generation)
+    }
+
+    /// fails with `Cancelled` when cancelled, only looks every few steps so
+    /// it can be called for every key
+    pub fn check(&self, step: usize) -> Result<()> {
+        if step.is_multiple_of(CHECK_EVERY) && self.is_cancelled() {
+            bail!(Cancelled);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newer_generation_cancels() {
+        let latest = Arc::new(AtomicU64::new(1));
+        let cancel = Cancel::new(Arc::clone(&latest), 1);
+        assert!(cancel.check(0).is_ok());
+
+        latest.fetch_add(1, Ordering::SeqCst);
+        assert!(cancel.check(1).is_ok());
+        assert!(cancel.check(CHECK_EVERY).unwrap_err().is::<Cancelled>());
+        assert!(!Cancel::default().is_cancelled());
+    }
+}
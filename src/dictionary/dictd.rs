@@ -0,0 +1,133 @@
+use super::dictzip::write_dictzip;
+use super::files::{DictFiles, DictFormat};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// collects entries and writes them as a dictd `.index` + `.dict.dz` pair
+#[derive(Debug, Default)]
+pub struct DictdWriter {
+    data: Vec<u8>,
+    /// headword, offset and length of every entry in `data`
+    index: Vec<(String, u64, u64)>,
+}
+
+impl DictdWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `text` is stored as is, FreeDict style entries repeat the headword on the first line
+    pub fn add(&mut self, headword: &str, text: &str) {
+        let offset = self.data.len() as u64;
+        self.data.extend_from_slice(text.trim_end().as_bytes());
+        self.data.push(b'\n');
+        let length = self.data.len() as u64 - offset;
+        self.index.push((headword.to_string(), offset, length));
+    }
+
+    /// another headword for the entry added last, e.g. an alternative spelling
+    pub fn add_alias(&mut self, headword: &str) {
+        if let Some((_, offset, length)) = self.index.last() {
+            let location = (headword.to_string(), *offset, *length);
+            self.index.push(location);
+        }
+    }
+
+    /// a `00-database-*` entry shown in the info panel, e.g. "short" for the title
+    pub fn add_metadata(&mut self, name: &str, value: &str) {
+        let headword = format!("00-database-{}", name);
+        let text = format!("{}\n{}", headword, value);
+        self.add(&headword, &text);
+    }
+
+    /// writes `<name>.index` and `<name>.dict.dz` into `dir`
+    pub fn write(mut self, dir: &Path, name: &str) -> Result<DictFiles> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create dictionary directory: {:?}", dir))?;
+
+        // dictd expects the index sorted, metadata entries end up first
+        self.index.sort_by(|a, b| a.0.cmp(&b.0));
+        let index: String = self
+            .index
+            .iter()
+            .map(|(headword, offset, length)| {
+                format!(
+                    "{}\t{}\t{}\n",
+                    headword.replace(['\t', '\n'], " "),
+                    encode_dict_number(*offset),
+                    encode_dict_number(*length)
+                )
+            })
+            .collect();
+
+        let files = DictFiles {
+            format: DictFormat::Dictd,
+            index: dir.join(format!("{}.index", name)),
+            data: dir.join(format!("{}.dict.dz", name)),
+            ifo: None,
+            synonyms: None,
+        };
+        write_dictzip(&files.data, &self.data)?;
+        fs::write(&files.index, index)
+            .with_context(|| format!("Failed to write index file: {:?}", files.index))?;
+        Ok(files)
+    }
+}
+
+/// dictd's base64 numbers, most significant digit first
+fn encode_dict_number(mut value: u64) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(ALPHABET[(value % 64) as usize]);
+        value /= 64;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::{CacheManager, DictZipReader};
+
+    #[test]
+    fn test_encodes_dict_numbers() {
+        for value in [0, 1, 63, 64, 4095, 123_456_789] {
+            let encoded = encode_dict_number(value);
+            assert_eq!(CacheManager::decode_dict_number(&encoded).unwrap(), value);
+        }
+        assert_eq!(encode_dict_number(64), "BA");
+    }
+
+    #[test]
+    fn test_writes_readable_dictionary() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let mut writer = DictdWriter::new();
+        writer.add("Haus", "Haus <n>\nhouse");
+        writer.add("Apfel", "Apfel\napple");
+        writer.add_alias("Äpfel");
+        writer.add_metadata("short", "Test");
+        let files = writer.write(dir, "test").unwrap();
+        assert_eq!(DictFiles::detect(dir), Some(files.clone()));
+
+        let index = fs::read_to_string(&files.index).unwrap();
+        let lines: Vec<&str> = index.lines().collect();
+        assert!(lines[0].starts_with("00-database-short\t"));
+        assert!(lines[1].starts_with("Apfel\t"));
+        assert_eq!(lines[1].split('\t').nth(1), lines[3].split('\t').nth(1));
+
+        let data = DictZipReader::open(&files.data).unwrap();
+        let parts: Vec<&str> = lines[2].split('\t').collect();
+        let offset = CacheManager::decode_dict_number(parts[1]).unwrap();
+        let length = CacheManager::decode_dict_number(parts[2]).unwrap();
+        assert_eq!(data.read(offset, length).unwrap(), b"Haus <n>\nhouse\n");
+    }
+}
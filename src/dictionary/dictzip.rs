@@ -0,0 +1,451 @@
+use anyhow::{anyhow, bail, Context, Result};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const FLAG_HCRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+
+/// uncompressed chunk size dictzip itself uses
+const CHUNK_LEN: usize = 58315;
+
+/// most chunks an RA field holds, the gzip extra field it sits in (4 byte
+/// subfield header, 6 bytes of settings, 2 per chunk) is limited to u16::MAX
+const MAX_CHUNKS: usize = (u16::MAX as usize - 10) / 2;
+
+/// number of decompressed chunks kept around, dictzip chunks are ~58KB each
+const CHUNK_CACHE_SIZE: usize = 16;
+
+/// random access reader for dictzip (.dict.dz) files
+///
+/// dictzip is gzip with an extra "RA" header field that lists the compressed
+/// size of every chunk, so single chunks can be inflated on their own.
+/// plain gzip files without that field are inflated into memory once,
+/// and uncompressed files (e.g. a StarDict .dict) are read directly.
+pub struct DictZipReader {
+    source: Source,
+}
+
+enum Source {
+    Chunked(Mutex<ChunkedFile>),
+    Memory(Vec<u8>),
+    Uncompressed(Mutex<File>),
+}
+
+struct ChunkedFile {
+    file: File,
+    chunk_len: usize,
+    /// compressed (offset, size) of every chunk within the file
+    chunks: Vec<(u64, usize)>,
+    cache: ChunkCache,
+}
+
+struct ChunkCache {
+    capacity: usize,
+    /// most recently used chunk at the front
+    entries: VecDeque<(usize, Arc<Vec<u8>>)>,
+}
+
+struct Header {
+    chunk_len: usize,
+    chunk_sizes: Vec<usize>,
+    data_start: u64,
+}
+
+impl DictZipReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open dict file: {:?}", path))?;
+
+        let mut magic = [0u8; 2];
+        let is_gzip = file.read_exact(&mut magic).is_ok() && magic == GZIP_MAGIC;
+        file.seek(SeekFrom::Start(0))?;
+        if !is_gzip {
+            return Ok(Self {
+                source: Source::Uncompressed(Mutex::new(file)),
+            });
+        }
+
+        let source = match Self::read_header(&mut file)? {
+            Some(header) => {
+                let mut offset = header.data_start;
+                let chunks = header
+                    .chunk_sizes
+                    .iter()
+                    .map(|&size| {
+                        let chunk = (offset, size);
+                        offset += size as u64;
+                        chunk
+                    })
+                    .collect();
+
+                Source::Chunked(Mutex::new(ChunkedFile {
+                    file,
+                    chunk_len: header.chunk_len,
+                    chunks,
+                    cache: ChunkCache::new(CHUNK_CACHE_SIZE),
+                }))
+            }
+            None => {
+                file.seek(SeekFrom::Start(0))?;
+                let mut decoder = flate2::read::GzDecoder::new(BufReader::new(file));
+                let mut content = Vec::new();
+                decoder
+                    .read_to_end(&mut content)
+                    .context("Failed to decompress dictionary data")?;
+                Source::Memory(content)
+            }
+        };
+
+        Ok(Self { source })
+    }
+
+    /// reads `length` uncompressed bytes starting at `offset`
+    pub fn read(&self, offset: u64, length: u64) -> Result<Vec<u8>> {
+        match &self.source {
+            Source::Memory(content) => {
+                let start = usize::try_from(offset)?;
+                let end = start
+                    .checked_add(usize::try_from(length)?)
+                    .ok_or_else(|| anyhow!("Range overflow"))?;
+                content
+                    .get(start..end)
+                    .map(|s| s.to_vec())
+                    .ok_or_else(|| anyhow!("Range {}..{} out of bounds", start, end))
+            }
+            Source::Chunked(chunked) => {
+                let mut chunked = chunked
+                    .lock()
+                    .map_err(|_| anyhow!("Dictionary reader lock poisoned"))?;
+                chunked.read(offset, length)
+            }
+            Source::Uncompressed(file) => {
+                let mut file = file
+                    .lock()
+                    .map_err(|_| anyhow!("Dictionary reader lock poisoned"))?;
+                // the index may be corrupt, don't allocate past the end of the file
+                let file_len = file.metadata()?.len();
+                if offset.checked_add(length).is_none_or(|end| end > file_len) {
+                    bail!("Range {}+{} out of bounds", offset, length);
+                }
+                let mut buffer = vec![0u8; usize::try_from(length)?];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut buffer)
+                    .with_context(|| format!("Range {}+{} out of bounds", offset, length))?;
+                Ok(buffer)
+            }
+        }
+    }
+
+    /// parses the gzip header, returns None for gzip files without RA field
+    fn read_header(file: &mut File) -> Result<Option<Header>> {
+        let mut reader = BufReader::new(file);
+
+        let mut fixed = [0u8; 10];
+        reader
+            .read_exact(&mut fixed)
+            .context("Failed to read gzip header")?;
+        if fixed[0..2] != GZIP_MAGIC || fixed[2] != 8 {
+            bail!("Not a gzip file");
+        }
+        let flags = fixed[3];
+        let mut pos = fixed.len() as u64;
+
+        let mut header = None;
+
+        if flags & FLAG_EXTRA != 0 {
+            let xlen = read_u16(&mut reader)? as usize;
+            let mut extra = vec![0u8; xlen];
+            reader.read_exact(&mut extra)?;
+            pos += 2 + xlen as u64;
+            header = Self::parse_ra_field(&extra)?;
+        }
+
+        if flags & FLAG_NAME != 0 {
+            pos += skip_zero_terminated(&mut reader)?;
+        }
+        if flags & FLAG_COMMENT != 0 {
+            pos += skip_zero_terminated(&mut reader)?;
+        }
+        if flags & FLAG_HCRC != 0 {
+            read_u16(&mut reader)?;
+            pos += 2;
+        }
+
+        Ok(header.map(|(chunk_len, chunk_sizes)| Header {
+            chunk_len,
+            chunk_sizes,
+            data_start: pos,
+        }))
+    }
+
+    fn parse_ra_field(extra: &[u8]) -> Result<Option<(usize, Vec<usize>)>> {
+        let mut rest = extra;
+
+        while rest.len() >= 4 {
+            let id = [rest[0], rest[1]];
+            let len = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+            let data = rest
+                .get(4..4 + len)
+                .ok_or_else(|| anyhow!("Truncated gzip extra field"))?;
+
+            if id == *b"RA" {
+                if data.len() < 6 {
+                    bail!("Truncated dictzip header");
+                }
+                let field = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]) as usize;
+                let (version, chunk_len, chunk_count) = (field(0), field(2), field(4));
+                if version != 1 {
+                    bail!("Unsupported dictzip version {}", version);
+                }
+                if chunk_len == 0 {
+                    bail!("Invalid dictzip chunk length");
+                }
+                if data.len() < 6 + chunk_count * 2 {
+                    bail!("Truncated dictzip chunk table");
+                }
+                let sizes = (0..chunk_count).map(|i| field(6 + i * 2)).collect();
+                return Ok(Some((chunk_len, sizes)));
+            }
+
+            rest = &rest[4 + len..];
+        }
+
+        Ok(None)
+    }
+}
+
+impl ChunkedFile {
+    fn read(&mut self, offset: u64, length: u64) -> Result<Vec<u8>> {
+        let chunk_len = self.chunk_len as u64;
+        let data_len = chunk_len * self.chunks.len() as u64;
+        if offset.checked_add(length).is_none_or(|end| end > data_len) {
+            bail!("Range {}+{} out of bounds", offset, length);
+        }
+        let mut result = Vec::with_capacity(length as usize);
+        if length == 0 {
+            return Ok(result);
+        }
+
+        let first = (offset / chunk_len) as usize;
+        let last = ((offset + length - 1) / chunk_len) as usize;
+
+        for index in first..=last {
+            let chunk = self.chunk(index)?;
+            let chunk_start = index as u64 * chunk_len;
+            let from = offset.saturating_sub(chunk_start) as usize;
+            let to = ((offset + length - chunk_start) as usize).min(chunk.len());
+            if from >= to {
+                bail!("Range {}+{} out of bounds", offset, length);
+            }
+            result.extend_from_slice(&chunk[from..to]);
+        }
+
+        Ok(result)
+    }
+
+    fn chunk(&mut self, index: usize) -> Result<Arc<Vec<u8>>> {
+        if let Some(chunk) = self.cache.get(index) {
+            return Ok(chunk);
+        }
+
+        let &(offset, size) = self
+            .chunks
+            .get(index)
+            .ok_or_else(|| anyhow!("Chunk {} out of range", index))?;
+
+        let mut compressed = vec![0u8; size];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file
+            .read_exact(&mut compressed)
+            .context("Failed to read dictzip chunk")?;
+
+        // every chunk is flushed with Z_FULL_FLUSH, so it inflates without any history
+        let mut inflater = Decompress::new(false);
+        let mut chunk = Vec::with_capacity(self.chunk_len);
+        inflater
+            .decompress_vec(&compressed, &mut chunk, FlushDecompress::Sync)
+            .context("Failed to decompress dictzip chunk")?;
+
+        let chunk = Arc::new(chunk);
+        self.cache.insert(index, Arc::clone(&chunk));
+        Ok(chunk)
+    }
+}
+
+impl ChunkCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn get(&mut self, index: usize) -> Option<Arc<Vec<u8>>> {
+        let pos = self.entries.iter().position(|(i, _)| *i == index)?;
+        let entry = self.entries.remove(pos)?;
+        let chunk = Arc::clone(&entry.1);
+        self.entries.push_front(entry);
+        Some(chunk)
+    }
+
+    fn insert(&mut self, index: usize, chunk: Arc<Vec<u8>>) {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_back();
+        }
+        self.entries.push_front((index, chunk));
+    }
+}
+
+/// writes `data` as a dictzip file that `DictZipReader` and dictd can read
+pub fn write_dictzip(path: &Path, data: &[u8]) -> Result<()> {
+    write_chunked(path, data, CHUNK_LEN)
+}
+
+fn write_chunked(path: &Path, data: &[u8], chunk_len: usize) -> Result<()> {
+    if data.len().div_ceil(chunk_len) > MAX_CHUNKS {
+        bail!("Too much data for a dictzip file");
+    }
+
+    // every chunk is flushed with Z_FULL_FLUSH so it can be inflated on its own
+    let mut compressor = Compress::new(Compression::default(), false);
+    let mut sizes = Vec::new();
+    let mut body = Vec::new();
+
+    let chunks: Vec<&[u8]> = data.chunks(chunk_len).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let flush = if i == chunks.len() - 1 {
+            FlushCompress::Finish
+        } else {
+            FlushCompress::Full
+        };
+        let mut out = Vec::with_capacity(chunk_len * 2 + 64);
+        let before = compressor.total_out();
+        compressor
+            .compress_vec(chunk, &mut out, flush)
+            .context("Failed to compress dictzip chunk")?;
+        let size = u16::try_from(compressor.total_out() - before)
+            .map_err(|_| anyhow!("Compressed dictzip chunk too large"))?;
+        sizes.push(size);
+        body.extend_from_slice(&out);
+    }
+    if chunks.is_empty() {
+        compressor.compress_vec(&[], &mut body, FlushCompress::Finish)?;
+    }
+
+    let mut ra = Vec::new();
+    for field in [1, chunk_len as u16, sizes.len() as u16]
+        .into_iter()
+        .chain(sizes)
+    {
+        ra.extend_from_slice(&field.to_le_bytes());
+    }
+
+    let file =
+        File::create(path).with_context(|| format!("Failed to create dict file: {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(&[
+        GZIP_MAGIC[0],
+        GZIP_MAGIC[1],
+        8,
+        FLAG_EXTRA,
+        0,
+        0,
+        0,
+        0,
+        0,
+        3,
+    ])?;
+    writer.write_all(&((ra.len() + 4) as u16).to_le_bytes())?;
+    writer.write_all(b"RA")?;
+    writer.write_all(&(ra.len() as u16).to_le_bytes())?;
+    writer.write_all(&ra)?;
+    writer.write_all(&body)?;
+
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(data);
+    writer.write_all(&crc.finalize().to_le_bytes())?;
+    writer.write_all(&(data.len() as u32).to_le_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_u16(reader: &mut impl Read) -> Result<u16> {
+    let mut buf = [0u8; 2];
+    reader.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn skip_zero_terminated(reader: &mut impl Read) -> Result<u64> {
+    let mut skipped = 0;
+    let mut byte = [0u8; 1];
+    loop {
+        reader.read_exact(&mut byte)?;
+        skipped += 1;
+        if byte[0] == 0 {
+            return Ok(skipped);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_data() -> Vec<u8> {
+        (0..5000)
+            .flat_map(|i| format!("entry {}\n", i).into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_reads_ranges_across_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chunks.dict.dz");
+        let data = sample_data();
+        write_chunked(&path, &data, 1000).unwrap();
+
+        let reader = DictZipReader::open(&path).unwrap();
+        assert_eq!(reader.read(0, 8).unwrap(), &data[0..8]);
+        assert_eq!(reader.read(995, 20).unwrap(), &data[995..1015]);
+        assert_eq!(reader.read(1500, 3000).unwrap(), &data[1500..4500]);
+        assert!(reader.read(data.len() as u64, u64::MAX - 100).is_err());
+    }
+
+    #[test]
+    fn test_plain_gzip_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plain.dict.dz");
+        let data = sample_data();
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        encoder.finish().unwrap();
+
+        let reader = DictZipReader::open(&path).unwrap();
+        assert_eq!(reader.read(1200, 30).unwrap(), &data[1200..1230]);
+        assert!(reader.read(data.len() as u64, 10).is_err());
+    }
+
+    #[test]
+    fn test_rejects_out_of_range_reads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("uncompressed.dict");
+        let data = sample_data();
+        std::fs::write(&path, &data).unwrap();
+
+        let reader = DictZipReader::open(&path).unwrap();
+        assert_eq!(reader.read(100, 20).unwrap(), &data[100..120]);
+        assert!(reader.read(100, u64::MAX - 50).is_err());
+        assert!(reader.read(data.len() as u64 - 5, 10).is_err());
+
+        let too_many = vec![b'x'; MAX_CHUNKS + 1];
+        assert!(write_chunked(&path, &too_many, 1).is_err());
+    }
+}
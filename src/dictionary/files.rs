@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// on-disk layout of an installed dictionary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DictFormat {
+    /// .index + .dict.dz
+    #[default]
+    Dictd,
+    /// .ifo + .idx[.gz] + .dict[.dz] + optional .syn
+    StarDict,
+}
+
+/// the files of one dictionary
+#[derive(Debug, Clone, PartialEq)]
+pub struct DictFiles {
+    pub format: DictFormat,
+    /// .index for dictd, .idx or .idx.gz for StarDict
+    pub index: PathBuf,
+    /// .dict.dz for dictd, .dict or .dict.dz for StarDict
+    pub data: PathBuf,
+    /// StarDict .ifo
+    pub ifo: Option<PathBuf>,
+    /// StarDict .syn
+    pub synonyms: Option<PathBuf>,
+}
+
+impl DictFiles {
+    /// files of `format` directly inside `dir`
+    pub fn locate(dir: &Path, format: DictFormat) -> Option<Self> {
+        let files: Vec<PathBuf> = fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        let find = |suffix: &str| {
+            files
+                .iter()
+                .find(|path| path.to_string_lossy().ends_with(suffix))
+                .cloned()
+        };
+
+        match format {
+            DictFormat::Dictd => Some(Self {
+                format,
+                index: find(".index")?,
+                data: find(".dict.dz")?,
+                ifo: None,
+                synonyms: None,
+            }),
+            DictFormat::StarDict => {
+                let ifo = find(".ifo")?;
+                Some(Self {
+                    format,
+                    index: find(".idx").or_else(|| find(".idx.gz"))?,
+                    data: find(".dict").or_else(|| find(".dict.dz"))?,
+                    ifo: Some(ifo),
+                    synonyms: find(".syn"),
+                })
+            }
+        }
+    }
+
+    /// whichever format the files in `dir` are in, dictd first
+    pub fn detect(dir: &Path) -> Option<Self> {
+        Self::locate(dir, DictFormat::Dictd).or_else(|| Self::locate(dir, DictFormat::StarDict))
+    }
+
+    /// files the headword index is built from
+    pub fn index_sources(&self) -> Vec<&Path> {
+        [Some(&self.index), self.ifo.as_ref(), self.synonyms.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(PathBuf::as_path)
+            .collect()
+    }
+}
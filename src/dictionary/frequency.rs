@@ -0,0 +1,125 @@
+use super::normalize::Normalizer;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// how common the words of a language are, used to rank prefix matches
+///
+/// read from a `word count` list like Hermit Dave's FrequencyWords,
+/// lines without a count are ranked in file order
+#[derive(Debug, Clone, Default)]
+pub struct FrequencyList {
+    /// (folded word, rank) sorted by word, 0 is the most frequent
+    ranks: Vec<(String, u32)>,
+}
+
+impl FrequencyList {
+    /// `normalizer` has to be the one of the headword index the list is used with
+    pub fn load(path: &Path, normalizer: Normalizer) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read frequency list: {:?}", path))?;
+        Ok(Self::parse(&text, normalizer))
+    }
+
+    fn parse(text: &str, normalizer: Normalizer) -> Self {
+        let mut words: Vec<(u64, String)> = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let word = fields.next()?.trim_start_matches('\u{feff}');
+                let count = fields.next().and_then(|c| c.parse().ok()).unwrap_or(0);
+                Some((count, normalizer.fold(word)))
+            })
+            .collect();
+        // stable, so lists without counts keep their order
+        words.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+
+        let mut ranks: Vec<(String, u32)> = words
+            .into_iter()
+            .enumerate()
+            .map(|(rank, (_, word))| (word, rank as u32))
+            .collect();
+        // spellings folding to the same key keep the better rank
+        ranks.sort();
+        ranks.dedup_by(|a, b| a.0 == b.0);
+        Self { ranks }
+    }
+
+    /// rank of a folded word, None when it isn't in the list
+    pub fn rank(&self, key: &str) -> Option<u32> {
+        let i = self
+            .ranks
+            .binary_search_by(|(word, _)| word.as_str().cmp(key))
+            .ok()?;
+        Some(self.ranks[i].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::cancel::Cancel;
+    use crate::dictionary::trie::PrefixTrie;
+    use crate::dictionary::HeadwordIndex;
+
+    #[test]
+    fn test_ranks_by_count() {
+        let list =
+            FrequencyList::parse("there 300\nthe 1000\nThe 5\nthy 2\n", Normalizer::default());
+        assert_eq!(list.rank("the"), Some(0));
+        assert_eq!(list.rank("there"), Some(1));
+        assert_eq!(list.rank("thy"), Some(3));
+        assert_eq!(list.rank("thee"), None);
+        assert_eq!(list.ranks.len(), 3);
+
+        let list = FrequencyList::parse("und\nder\n", Normalizer::default());
+        assert_eq!(list.rank("der"), Some(1));
+    }
+
+    #[test]
+    fn test_common_words_rank_first() {
+        let mut trie = PrefixTrie::new();
+        for (i, word) in ["tho", "thy", "the", "there", "thence"].iter().enumerate() {
+            trie.insert(word, i as u64, 1);
+        }
+        let bytes = HeadwordIndex::encode(&trie);
+        let headwords = |index: HeadwordIndex| -> Vec<String> {
+            index
+                .search_prefix("th", 10)
+                .into_iter()
+                .map(|(headword, ..)| headword)
+                .collect()
+        };
+
+        let index = HeadwordIndex::from_bytes(bytes.clone(), Normalizer::default()).unwrap();
+        assert_eq!(headwords(index), ["the", "tho", "thy", "there", "thence"]);
+
+        let list = FrequencyList::parse("the 900\nthere 80\nthy 3\n", Normalizer::default());
+        let index = HeadwordIndex::from_bytes(bytes, Normalizer::default())
+            .unwrap()
+            .with_frequencies(Some(list));
+        assert_eq!(headwords(index), ["the", "there", "thy", "tho", "thence"]);
+    }
+
+    #[test]
+    fn test_large_ranges_rank_frequent_words_first() {
+        let mut trie = PrefixTrie::new();
+        for i in 0..1000 {
+            trie.insert(&format!("word{:04}", i), i, 1);
+        }
+        trie.insert("word", 1000, 1);
+        let list = FrequencyList::parse("word0900\nword0500\n", Normalizer::default());
+        let index = HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), Normalizer::default())
+            .unwrap()
+            .with_frequencies(Some(list));
+
+        let words: Vec<String> = index
+            .prefix_matches("word", &Cancel::default())
+            .unwrap()
+            .map(|(word, ..)| word)
+            .collect();
+        assert_eq!(words.len(), 1001);
+        assert_eq!(words[..4], ["word", "word0900", "word0500", "word0000"]);
+        assert_eq!(words.iter().filter(|word| *word == "word0900").count(), 1);
+    }
+}
@@ -0,0 +1,247 @@
+use super::index::HeadwordIndex;
+use super::normalize::Normalizer;
+use super::table::{SortedTable, TableBytes};
+use anyhow::Result;
+use std::collections::HashMap;
+
+const MAGIC: &[u8; 4] = b"TDFT";
+const VERSION: u32 = 1;
+const POSTING_LEN: usize = 8;
+
+/// terms shorter than this only match exactly, not as a prefix
+const MIN_PREFIX_LEN: usize = 3;
+
+/// a headword location: (record in the headword index, location within the record)
+pub type EntryRef = (u32, u16);
+
+/// inverted index from terms in the definition text to the entries using them
+///
+/// each key is a folded term, the payload lists
+/// record u32 | location u16 | term frequency u16 per entry
+pub struct FullTextIndex {
+    table: SortedTable,
+    normalizer: Normalizer,
+}
+
+#[derive(Default)]
+struct Score {
+    matched_terms: usize,
+    exact_terms: usize,
+    frequency: u32,
+}
+
+impl FullTextIndex {
+    pub fn load(
+        bytes: TableBytes,
+        base: usize,
+        headwords: &HeadwordIndex,
+        normalizer: Normalizer,
+    ) -> Result<Self> {
+        let tag = Self::tag(headwords, normalizer);
+        let table = SortedTable::load(bytes, base, MAGIC, VERSION, tag)?;
+        Ok(Self { table, normalizer })
+    }
+
+    #[cfg(test)]
+    pub fn from_bytes(
+        bytes: Vec<u8>,
+        headwords: &HeadwordIndex,
+        normalizer: Normalizer,
+    ) -> Result<Self> {
+        Self::load(TableBytes::Owned(bytes), 0, headwords, normalizer)
+    }
+
+    /// postings refer to headword records, so both normalizers have to match
+    fn tag(headwords: &HeadwordIndex, normalizer: Normalizer) -> u64 {
+        (headwords.tag() << 32) | normalizer.tag()
+    }
+
+    /// reads every definition body once and encodes the inverted index
+    pub fn build(
+        headwords: &HeadwordIndex,
+        body: impl Fn(u64, u64) -> Option<String>,
+        normalizer: Normalizer,
+    ) -> Vec<u8> {
+        let mut entries = Vec::new();
+        for record in 0..headwords.len() {
+            for (n, (_, offset, length)) in headwords.locations(record).into_iter().enumerate() {
+                entries.push((offset, length, (record as u32, n as u16)));
+            }
+        }
+        // read in file order so consecutive entries hit the same dictzip chunk
+        entries.sort_unstable();
+
+        let mut postings: HashMap<String, Vec<(EntryRef, u16)>> = HashMap::new();
+        for (offset, length, entry) in entries {
+            let Some(text) = body(offset, length) else {
+                continue;
+            };
+
+            let mut frequencies: HashMap<String, u16> = HashMap::new();
+            for term in tokenize(&text, normalizer) {
+                let count = frequencies.entry(term).or_default();
+                *count = count.saturating_add(1);
+            }
+            for (term, frequency) in frequencies {
+                postings.entry(term).or_default().push((entry, frequency));
+            }
+        }
+
+        let mut terms: Vec<(String, Vec<u8>)> = postings
+            .into_iter()
+            .map(|(term, mut list)| {
+                list.sort_unstable();
+                let mut payload = Vec::with_capacity(list.len() * POSTING_LEN);
+                for ((record, n), frequency) in list {
+                    payload.extend_from_slice(&record.to_le_bytes());
+                    payload.extend_from_slice(&n.to_le_bytes());
+                    payload.extend_from_slice(&frequency.to_le_bytes());
+                }
+                (term, payload)
+            })
+            .collect();
+        terms.sort_by(|a, b| a.0.cmp(&b.0));
+
+        SortedTable::encode(
+            terms
+                .iter()
+                .map(|(term, payload)| (term.as_bytes(), payload.as_slice())),
+            MAGIC,
+            VERSION,
+            Self::tag(headwords, normalizer),
+        )
+    }
+
+    /// entries mentioning every word of `query`, ranked by exact word
+    /// matches first and term frequency second
+    pub fn search(&self, query: &str) -> Vec<EntryRef> {
+        let terms = tokenize(query, self.normalizer);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scores: HashMap<EntryRef, Score> = HashMap::new();
+        for term in &terms {
+            let range = if term.chars().count() >= MIN_PREFIX_LEN {
+                self.table.prefix_range(term.as_bytes())
+            } else {
+                let start = self.table.lower_bound(term.as_bytes());
+                let exact = start < self.table.len() && self.table.key(start) == term.as_bytes();
+                start..start + usize::from(exact)
+            };
+
+            // best match of this term per entry
+            let mut term_scores: HashMap<EntryRef, (bool, u16)> = HashMap::new();
+            for i in range {
+                let exact = self.table.key(i) == term.as_bytes();
+                for posting in self.table.payload(i).chunks_exact(POSTING_LEN) {
+                    let record = u32::from_le_bytes(posting[0..4].try_into().unwrap());
+                    let n = u16::from_le_bytes([posting[4], posting[5]]);
+                    let frequency = u16::from_le_bytes([posting[6], posting[7]]);
+
+                    let best = term_scores.entry((record, n)).or_insert((exact, 0));
+                    best.0 |= exact;
+                    best.1 = best.1.saturating_add(frequency);
+                }
+            }
+
+            for (entry, (exact, frequency)) in term_scores {
+                let score = scores.entry(entry).or_default();
+                score.matched_terms += 1;
+                score.exact_terms += usize::from(exact);
+                score.frequency += u32::from(frequency);
+            }
+        }
+
+        let mut results: Vec<(EntryRef, Score)> = scores
+            .into_iter()
+            .filter(|(_, score)| score.matched_terms == terms.len())
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.1.exact_terms
+                .cmp(&a.1.exact_terms)
+                .then(b.1.frequency.cmp(&a.1.frequency))
+                .then(a.0.cmp(&b.0))
+        });
+
+        results.into_iter().map(|(entry, _)| entry).collect()
+    }
+}
+
+/// folded words of `text`
+pub fn tokenize(text: &str, normalizer: Normalizer) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| normalizer.fold(word))
+        .collect()
+}
+
+/// dictd definitions repeat the headword (and pronunciation) on the first line
+pub fn definition_body(text: &str) -> &str {
+    let text = text.trim_start();
+    match text.split_once('\n') {
+        Some((_, body)) => body,
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::dictzip::DictZipReader;
+    use crate::dictionary::trie::PrefixTrie;
+
+    #[test]
+    fn test_ranks_exact_words_before_prefixes() {
+        let definitions = [
+            "Haus\n  house, home",
+            "Gebäude\n  building, house, house",
+            "Haushalt\n  household",
+            "Heim\n  home",
+        ];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fulltext.dict.dz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut trie = PrefixTrie::new();
+        let mut offset = 0;
+        for definition in definitions {
+            std::io::Write::write_all(&mut encoder, definition.as_bytes()).unwrap();
+            let headword = definition.lines().next().unwrap();
+            trie.insert(headword, offset, definition.len() as u64);
+            offset += definition.len() as u64;
+        }
+        encoder.finish().unwrap();
+
+        let normalizer = Normalizer::default();
+        let headwords =
+            HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), normalizer).unwrap();
+        let data = DictZipReader::open(&path).unwrap();
+        let body = |offset, length| {
+            let bytes = data.read(offset, length).ok()?;
+            Some(definition_body(&String::from_utf8_lossy(&bytes)).to_string())
+        };
+        let fulltext = FullTextIndex::from_bytes(
+            FullTextIndex::build(&headwords, body, normalizer),
+            &headwords,
+            normalizer,
+        )
+        .unwrap();
+
+        let headword =
+            |(record, n): EntryRef| headwords.locations(record as usize)[n as usize].0.clone();
+        let found: Vec<String> = fulltext.search("house").into_iter().map(headword).collect();
+        assert_eq!(found, vec!["Gebäude", "Haus", "Haushalt"]);
+
+        let found: Vec<String> = fulltext
+            .search("home HOUSE")
+            .into_iter()
+            .map(headword)
+            .collect();
+        assert_eq!(found, vec!["Haus"]);
+    }
+}
@@ -0,0 +1,92 @@
+use super::cancel::Cancel;
+use super::table::SortedTable;
+use anyhow::Result;
+
+/// allowed edit distance for a query, short words tolerate fewer typos
+pub fn max_distance(query: &str) -> usize {
+    if query.chars().count() <= 4 {
+        1
+    } else {
+        2
+    }
+}
+
+/// walks the sorted keys like a trie and returns (record, distance) pairs
+/// for every key within `max_distance` edits (optimal string alignment)
+///
+/// rows of the distance matrix are shared between keys with a common prefix,
+/// and once a whole row exceeds the bound every key below that prefix is skipped
+pub fn search(
+    table: &SortedTable,
+    query: &str,
+    max_distance: usize,
+    cancel: &Cancel,
+) -> Result<Vec<(usize, usize)>> {
+    let query: Vec<char> = query.chars().collect();
+    let mut path: Vec<char> = Vec::new();
+    let mut rows: Vec<Vec<usize>> = vec![(0..=query.len()).collect()];
+    let mut matches = Vec::new();
+
+    let mut i = 0;
+    let mut step = 0;
+    while i < table.len() {
+        cancel.check(step)?;
+        step += 1;
+        let key = String::from_utf8_lossy(table.key(i));
+        let key: Vec<char> = key.chars().collect();
+
+        let common = path.iter().zip(&key).take_while(|(a, b)| a == b).count();
+        path.truncate(common);
+        rows.truncate(common + 1);
+
+        let mut pruned = false;
+        for &ch in &key[common..] {
+            let row = next_row(&rows, &path, &query, ch);
+            let exceeded = row.iter().all(|&d| d > max_distance);
+            path.push(ch);
+            rows.push(row);
+
+            if exceeded {
+                pruned = true;
+                break;
+            }
+        }
+
+        if pruned {
+            let prefix: String = path.iter().collect();
+            i = table.prefix_range(prefix.as_bytes()).end;
+            continue;
+        }
+
+        let distance = rows[rows.len() - 1][query.len()];
+        if distance <= max_distance {
+            matches.push((i, distance));
+        }
+        i += 1;
+    }
+
+    Ok(matches)
+}
+
+fn next_row(rows: &[Vec<usize>], path: &[char], query: &[char], ch: char) -> Vec<usize> {
+    let prev = &rows[rows.len() - 1];
+    let mut row = Vec::with_capacity(query.len() + 1);
+    row.push(prev[0] + 1);
+
+    for j in 1..=query.len() {
+        let cost = usize::from(query[j - 1] != ch);
+        let mut distance = (prev[j] + 1).min(row[j - 1] + 1).min(prev[j - 1] + cost);
+
+        // transposition of two adjacent characters
+        if j > 1 && rows.len() > 1 {
+            let prev_ch = path[path.len() - 1];
+            if query[j - 1] == prev_ch && query[j - 2] == ch {
+                distance = distance.min(rows[rows.len() - 2][j - 2] + 1);
+            }
+        }
+
+        row.push(distance);
+    }
+
+    row
+}
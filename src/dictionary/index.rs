@@ -0,0 +1,406 @@
+use super::cancel::Cancel;
+use super::frequency::FrequencyList;
+use super::fuzzy;
+use super::normalize::Normalizer;
+use super::pattern::Pattern;
+use super::table::{read_u64, SortedTable, TableBytes};
+use super::trie::{self, Location, PrefixTrie};
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"TDIX";
+const VERSION: u32 = 4;
+
+/// headword index backed by a sorted table, searched without deserializing
+///
+/// clones share the table, searches hand one to their lazy `Matches`
+#[derive(Clone)]
+pub struct HeadwordIndex {
+    table: SortedTable,
+    normalizer: Normalizer,
+    /// ranks prefix matches by how common they are, not part of the cache
+    frequencies: Option<Arc<FrequencyList>>,
+}
+
+impl HeadwordIndex {
+    pub fn load(bytes: TableBytes, base: usize, normalizer: Normalizer) -> Result<Self> {
+        let table = SortedTable::load(bytes, base, MAGIC, VERSION, normalizer.tag())?;
+        Ok(Self {
+            table,
+            normalizer,
+            frequencies: None,
+        })
+    }
+
+    pub fn with_frequencies(mut self, frequencies: Option<FrequencyList>) -> Self {
+        self.frequencies = frequencies.map(Arc::new);
+        self
+    }
+
+    pub fn encode(trie: &PrefixTrie) -> Vec<u8> {
+        // payload per location: headword_len u16 | headword | offset u64 | length u64
+        // the headword is left empty when it equals the key
+        let mut entries: Vec<(&[u8], Vec<u8>)> = trie
+            .iter()
+            .map(|(key, locations)| {
+                let mut payload = Vec::new();
+                for (headword, offset, length) in locations {
+                    let headword = if headword.as_bytes() == key {
+                        &[][..]
+                    } else {
+                        headword.as_bytes()
+                    };
+                    payload.extend_from_slice(&(headword.len() as u16).to_le_bytes());
+                    payload.extend_from_slice(headword);
+                    payload.extend_from_slice(&offset.to_le_bytes());
+                    payload.extend_from_slice(&length.to_le_bytes());
+                }
+                (key, payload)
+            })
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        SortedTable::encode(
+            entries.iter().map(|(key, payload)| (*key, &payload[..])),
+            MAGIC,
+            VERSION,
+            trie.normalizer().tag(),
+        )
+    }
+
+    #[cfg(test)]
+    pub fn from_bytes(bytes: Vec<u8>, normalizer: Normalizer) -> Result<Self> {
+        Self::load(TableBytes::Owned(bytes), 0, normalizer)
+    }
+
+    /// headwords starting with `prefix`, the whole range is ranked up front
+    /// and the locations are read lazily
+    pub fn prefix_matches(&self, prefix: &str, cancel: &Cancel) -> Result<Matches> {
+        if prefix.is_empty() {
+            return Ok(self.matches(Vec::new()));
+        }
+
+        let prefix_lower = self.normalizer.fold(prefix);
+        let range = self.table.prefix_range(prefix_lower.as_bytes());
+        let records = self.rank(range, &prefix_lower, cancel)?;
+        Ok(self.matches(records))
+    }
+
+    #[cfg(test)]
+    pub fn search_prefix(&self, prefix: &str, limit: usize) -> Vec<Location> {
+        self.prefix_matches(prefix, &Cancel::default())
+            .unwrap()
+            .take(limit)
+            .collect()
+    }
+
+    /// every headword starting with `prefix` whose folded key passes `filter`,
+    /// ranked like prefix matches
+    pub fn search_prefix_where(
+        &self,
+        prefix: &str,
+        filter: impl Fn(&str) -> bool,
+        cancel: &Cancel,
+    ) -> Result<Vec<Location>> {
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let prefix_lower = self.normalizer.fold(prefix);
+        let mut results: Vec<(String, Location)> = Vec::new();
+        for (step, i) in self.table.prefix_range(prefix_lower.as_bytes()).enumerate() {
+            cancel.check(step)?;
+            let key = self.key(i);
+            if filter(&key) {
+                results.extend(
+                    self.locations(i)
+                        .into_iter()
+                        .map(|location| (key.clone(), location)),
+                );
+            }
+        }
+
+        trie::rank_prefix_matches(&mut results, &prefix_lower, self.frequencies.as_deref());
+        Ok(results.into_iter().map(|(_, location)| location).collect())
+    }
+
+    /// headwords within a small edit distance of `query`, closest first
+    pub fn search_fuzzy(&self, query: &str, cancel: &Cancel) -> Result<Matches> {
+        if query.is_empty() {
+            return Ok(self.matches(Vec::new()));
+        }
+
+        let query_lower = self.normalizer.fold(query);
+        let max_distance = fuzzy::max_distance(&query_lower);
+
+        let mut results: Vec<(usize, String, usize)> =
+            fuzzy::search(&self.table, &query_lower, max_distance, cancel)?
+                .into_iter()
+                .map(|(i, distance)| (distance, self.key(i), i))
+                .collect();
+
+        results.sort_by(|a, b| match a.0.cmp(&b.0) {
+            Ordering::Equal => match a.1.len().cmp(&b.1.len()) {
+                Ordering::Equal => a.1.cmp(&b.1),
+                other => other,
+            },
+            other => other,
+        });
+
+        let records = results.into_iter().map(|(_, _, i)| i).collect();
+        Ok(self.matches(records))
+    }
+
+    /// headwords matching a wildcard or regex pattern, shorter keys first
+    pub fn search_pattern(&self, pattern: &Pattern, cancel: &Cancel) -> Result<Matches> {
+        let headwords = |i| {
+            self.locations(i)
+                .into_iter()
+                .map(|(headword, _, _)| headword)
+                .collect()
+        };
+        let records = pattern.search(&self.table, self.normalizer, headwords, cancel)?;
+
+        self.ranked_locations(records, "", cancel)
+    }
+
+    /// every location of the given records, ranked like prefix matches of `query`
+    pub fn ranked_locations(
+        &self,
+        records: Vec<usize>,
+        query: &str,
+        cancel: &Cancel,
+    ) -> Result<Matches> {
+        let records = self.rank(records.into_iter(), &self.fold(query), cancel)?;
+        Ok(self.matches(records))
+    }
+
+    /// orders records like `trie::rank_prefix_matches` orders keys: the exact
+    /// match, then common words, then shorter keys. records are in key order,
+    /// so the record number breaks ties without decoding any locations
+    fn rank(
+        &self,
+        records: impl Iterator<Item = usize>,
+        query_lower: &str,
+        cancel: &Cancel,
+    ) -> Result<Vec<usize>> {
+        // words missing from the list come after all listed ones
+        let frequencies = self.frequencies.as_deref();
+        let mut ranked = Vec::new();
+        for (step, i) in records.enumerate() {
+            cancel.check(step)?;
+            let key = self.table.key(i);
+            let rank = frequencies.map_or(0, |list| {
+                list.rank(&String::from_utf8_lossy(key)).unwrap_or(u32::MAX)
+            });
+            ranked.push((key != query_lower.as_bytes(), rank, key.len(), i));
+        }
+        ranked.sort_unstable();
+        Ok(ranked.into_iter().map(|(.., i)| i).collect())
+    }
+
+    fn matches(&self, records: Vec<usize>) -> Matches {
+        Matches {
+            index: self.clone(),
+            records: records.into_iter(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// the record whose key is the folded `key`
+    pub fn record_of(&self, key: &str) -> Option<usize> {
+        self.table.find(key.as_bytes())
+    }
+
+    pub fn fold(&self, word: &str) -> String {
+        self.normalizer.fold(word)
+    }
+
+    pub fn key(&self, i: usize) -> String {
+        String::from_utf8_lossy(self.table.key(i)).into_owned()
+    }
+
+    /// every location stored in record `i`, stops at the first truncated one
+    pub fn locations(&self, i: usize) -> Vec<Location> {
+        let mut payload = self.table.payload(i);
+        let mut locations = Vec::new();
+
+        while payload.len() >= 2 {
+            let headword_len = u16::from_le_bytes([payload[0], payload[1]]) as usize;
+            let Some(rest) = payload.get(2 + headword_len..) else {
+                break;
+            };
+            if rest.len() < 16 {
+                break;
+            }
+
+            let headword = if headword_len == 0 {
+                self.key(i)
+            } else {
+                String::from_utf8_lossy(&payload[2..2 + headword_len]).into_owned()
+            };
+            locations.push((headword, read_u64(rest, 0), read_u64(rest, 8)));
+            payload = &rest[16..];
+        }
+
+        locations
+    }
+
+    pub fn location(&self, record: usize, n: usize) -> Option<Location> {
+        self.locations(record).into_iter().nth(n)
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// identifies the record numbering that secondary indexes refer to
+    pub fn tag(&self) -> u64 {
+        (u64::from(VERSION) << 28) | self.normalizer.tag()
+    }
+}
+
+/// locations of matching records in rank order, decoded as they are read
+pub struct Matches {
+    index: HeadwordIndex,
+    records: std::vec::IntoIter<usize>,
+    pending: VecDeque<Location>,
+}
+
+impl Iterator for Matches {
+    type Item = Location;
+
+    fn next(&mut self) -> Option<Location> {
+        while self.pending.is_empty() {
+            let record = self.records.next()?;
+            self.pending.extend(self.index.locations(record));
+        }
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoded_index_matches_trie() {
+        let mut trie = PrefixTrie::new();
+        trie.insert("hello", 0, 10);
+        trie.insert("help", 10, 8);
+        trie.insert("hero", 18, 12);
+        trie.insert("Hel", 30, 5);
+        trie.insert("hel", 35, 5);
+        trie.insert("HELP", 40, 6);
+
+        let index =
+            HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), Normalizer::default()).unwrap();
+        assert_eq!(index.len(), 4);
+        assert_eq!(
+            index.search_prefix("hel", 10),
+            trie.search_prefix("hel", 10)
+        );
+        assert_eq!(
+            index.search_prefix("her", 10),
+            vec![("hero".into(), 18, 12)]
+        );
+        assert!(index.search_prefix("x", 10).is_empty());
+        assert_eq!(index.search_prefix("help", 10)[1].0, "HELP");
+    }
+
+    #[test]
+    fn test_fuzzy_search() {
+        let mut trie = PrefixTrie::new();
+        for (i, word) in ["house", "horse", "mouse", "hose", "household", "tree"]
+            .iter()
+            .enumerate()
+        {
+            trie.insert(word, i as u64, 1);
+        }
+        let index =
+            HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), Normalizer::default()).unwrap();
+
+        let words = |query| {
+            index
+                .search_fuzzy(query, &Cancel::default())
+                .unwrap()
+                .map(|(word, _, _)| word)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(words("huose"), vec!["hose", "house", "horse", "mouse"]);
+        assert_eq!(words("tre"), vec!["tree"]);
+        assert!(words("xyz").is_empty());
+    }
+
+    #[test]
+    fn test_folds_queries_like_keys() {
+        let normalizer = Normalizer::for_language("deu", true);
+        let mut trie = PrefixTrie::with_normalizer(normalizer);
+        trie.insert("Straße", 0, 10);
+        trie.insert("Café", 10, 10);
+        let index = HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), normalizer).unwrap();
+
+        assert_eq!(index.search_prefix("strasse", 10)[0].0, "Straße");
+        assert_eq!(index.search_prefix("CAFE", 10)[0].0, "Café");
+        assert!(HeadwordIndex::from_bytes(
+            HeadwordIndex::encode(&trie),
+            Normalizer::for_language("deu", false)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_pattern_search() {
+        let mut trie = PrefixTrie::new();
+        for (i, word) in ["nation", "station", "national", "house", "unfehlbar"]
+            .iter()
+            .enumerate()
+        {
+            trie.insert(word, i as u64, 1);
+        }
+        let index =
+            HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), Normalizer::default()).unwrap();
+
+        let words = |query| {
+            let pattern = Pattern::parse(query).unwrap().unwrap();
+            index
+                .search_pattern(&pattern, &Cancel::default())
+                .unwrap()
+                .map(|(word, _, _)| word)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(words("*tion"), vec!["nation", "station"]);
+        assert_eq!(words("h?use"), vec!["house"]);
+        assert_eq!(words("/^un.*bar$/"), vec!["unfehlbar"]);
+    }
+
+    #[test]
+    fn test_ranks_whole_prefix_range() {
+        let mut trie = PrefixTrie::new();
+        for i in 0..300 {
+            trie.insert(&format!("ab{:04}", i), i, 1);
+        }
+        trie.insert("an", 300, 1);
+        trie.insert("a", 301, 1);
+        let index =
+            HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), Normalizer::default()).unwrap();
+
+        let words: Vec<String> = index
+            .search_prefix("a", 3)
+            .into_iter()
+            .map(|(word, ..)| word)
+            .collect();
+        assert_eq!(words, ["a", "an", "ab0000"]);
+    }
+
+    #[test]
+    fn test_rejects_foreign_bytes() {
+        assert!(
+            HeadwordIndex::from_bytes(b"not an index".to_vec(), Normalizer::default()).is_err()
+        );
+    }
+}
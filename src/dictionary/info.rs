@@ -0,0 +1,205 @@
+use super::cache::CacheManager;
+use super::dictzip::DictZipReader;
+use super::files::DictFiles;
+use super::stardict::Ifo;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// metadata from the `00-database-*` entries of a dictd database or a StarDict .ifo
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DictionaryInfo {
+    /// `00-database-short`
+    pub title: Option<String>,
+    /// `00-database-info`
+    pub description: Option<String>,
+    /// `00-database-license`, or the first line of the description mentioning one
+    pub license: Option<String>,
+    /// `00-database-url`
+    pub url: Option<String>,
+    /// `00-database-utf8`, definitions are utf-8 rather than latin-1
+    pub utf8: bool,
+    /// `00-database-allchars`, headwords keep non-alphanumeric characters
+    pub allchars: bool,
+    /// `00-database-case-sensitive`
+    pub case_sensitive: bool,
+    /// any other metadata entries, without the `00-database-` prefix
+    pub other: Vec<(String, String)>,
+}
+
+/// metadata headwords, older databases spell them without the dash
+pub fn is_metadata_headword(word: &str) -> bool {
+    word.starts_with("00-database-") || word.starts_with("00database")
+}
+
+impl DictionaryInfo {
+    pub fn load(files: &DictFiles) -> Result<Self> {
+        match &files.ifo {
+            Some(ifo) => Ok(Self::from_ifo(&Ifo::load(ifo)?)),
+            None => Self::from_dictd(&files.index, &files.data),
+        }
+    }
+
+    /// StarDict keeps its metadata in the .ifo, and is always utf-8
+    fn from_ifo(ifo: &Ifo) -> Self {
+        let text = |key: &str| {
+            ifo.get(key)
+                .map(|value| value.replace("<br>", "\n").trim().to_string())
+        };
+
+        Self {
+            title: text("bookname"),
+            description: text("description"),
+            license: text("license"),
+            url: text("website"),
+            utf8: true,
+            other: ["author", "email", "date", "version", "wordcount"]
+                .iter()
+                .filter_map(|key| Some((key.to_string(), text(key)?)))
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// reads the metadata entries listed in the .index file
+    fn from_dictd(index_path: &Path, dict_path: &Path) -> Result<Self> {
+        let index_file = File::open(index_path)
+            .with_context(|| format!("Failed to open index file: {:?}", index_path))?;
+        let data = DictZipReader::open(dict_path)?;
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(index_file).lines() {
+            let line = line?;
+            let parts: Vec<&str> = line.trim().split('\t').collect();
+            if parts.len() < 3 || !is_metadata_headword(parts[0]) {
+                continue;
+            }
+
+            let offset = CacheManager::decode_dict_number(parts[1])?;
+            let length = CacheManager::decode_dict_number(parts[2])?;
+            let bytes = data.read(offset, length)?;
+            entries.push((
+                parts[0].to_string(),
+                String::from_utf8_lossy(&bytes).into_owned(),
+            ));
+        }
+
+        Ok(Self::from_entries(entries))
+    }
+
+    fn from_entries(entries: Vec<(String, String)>) -> Self {
+        let mut info = Self::default();
+
+        for (headword, text) in entries {
+            let name = headword
+                .trim_start_matches("00-database-")
+                .trim_start_matches("00database")
+                .to_lowercase();
+            let value = metadata_value(&headword, &text);
+
+            match name.as_str() {
+                "short" => info.title = Some(value),
+                "info" => info.description = Some(value),
+                "license" | "licence" => info.license = Some(value),
+                "url" => info.url = Some(value),
+                "utf8" => info.utf8 = true,
+                "allchars" => info.allchars = true,
+                "case-sensitive" | "casesensitive" => info.case_sensitive = true,
+                _ => info.other.push((name, value)),
+            }
+        }
+
+        if info.license.is_none() {
+            info.license = info.description.as_deref().and_then(|description| {
+                description
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| {
+                        let lower = line.to_lowercase();
+                        lower.contains("license") || lower.contains("licence")
+                    })
+                    .map(str::to_string)
+            });
+        }
+
+        info
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// the entry text without the repeated headword line
+fn metadata_value(headword: &str, text: &str) -> String {
+    let text = text.trim();
+    let body = match text.split_once('\n') {
+        Some((first, rest)) if first.trim() == headword => rest,
+        _ => text,
+    };
+
+    body.lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_metadata_entries() {
+        let entries = vec![
+            (
+                "00-database-short".to_string(),
+                "00-database-short\n     English-German FreeDict Dictionary ver. 0.3.7".to_string(),
+            ),
+            (
+                "00-database-info".to_string(),
+                "00-database-info\n  Maintained by FreeDict.\n  License: GPL-2.0-or-later\n"
+                    .to_string(),
+            ),
+            (
+                "00-database-url".to_string(),
+                "00-database-url\n     https://freedict.org/".to_string(),
+            ),
+            ("00-database-utf8".to_string(), String::new()),
+            (
+                "00databasealphabet".to_string(),
+                "abcdefghijklmnopqrstuvwxyz".to_string(),
+            ),
+        ];
+
+        let info = DictionaryInfo::from_entries(entries);
+        assert_eq!(
+            info.title.as_deref(),
+            Some("English-German FreeDict Dictionary ver. 0.3.7")
+        );
+        assert_eq!(
+            info.description.as_deref(),
+            Some("Maintained by FreeDict.\nLicense: GPL-2.0-or-later")
+        );
+        assert_eq!(info.license.as_deref(), Some("License: GPL-2.0-or-later"));
+        assert_eq!(info.url.as_deref(), Some("https://freedict.org/"));
+        assert!(info.utf8);
+        assert!(!info.case_sensitive);
+        assert_eq!(
+            info.other,
+            vec![(
+                "alphabet".to_string(),
+                "abcdefghijklmnopqrstuvwxyz".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_detects_metadata_headwords() {
+        assert!(is_metadata_headword("00-database-info"));
+        assert!(is_metadata_headword("00databaseshort"));
+        assert!(!is_metadata_headword("007"));
+    }
+}
@@ -0,0 +1,130 @@
+use super::cancel::Cancel;
+use super::index::HeadwordIndex;
+use super::trie::Location;
+use anyhow::Result;
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// finds the lemma of an inflected query, FreeDict headwords are lemmas
+///
+/// a query and a headword belong together when their Snowball stems are
+/// the same, e.g. "running" and "run" or "häuser" and "haus"
+pub struct Lemmatizer {
+    stemmer: Stemmer,
+}
+
+impl Lemmatizer {
+    /// Snowball rules for a FreeDict language code like "eng" or "DEU",
+    /// None for languages without any
+    pub fn for_language(lang: &str) -> Option<Self> {
+        let algorithm = match lang.to_lowercase().as_str() {
+            "ara" | "ar" => Algorithm::Arabic,
+            "dan" | "da" => Algorithm::Danish,
+            "nld" | "dut" | "nl" => Algorithm::Dutch,
+            "eng" | "en" => Algorithm::English,
+            "fin" | "fi" => Algorithm::Finnish,
+            "fra" | "fre" | "fr" => Algorithm::French,
+            "deu" | "ger" | "de" => Algorithm::German,
+            "ell" | "gre" | "el" => Algorithm::Greek,
+            "hun" | "hu" => Algorithm::Hungarian,
+            "ita" | "it" => Algorithm::Italian,
+            "nor" | "nob" | "nno" | "no" | "nb" | "nn" => Algorithm::Norwegian,
+            "por" | "pt" => Algorithm::Portuguese,
+            "ron" | "rum" | "ro" => Algorithm::Romanian,
+            "rus" | "ru" => Algorithm::Russian,
+            "spa" | "es" => Algorithm::Spanish,
+            "swe" | "sv" => Algorithm::Swedish,
+            "tam" | "ta" => Algorithm::Tamil,
+            "tur" | "tr" => Algorithm::Turkish,
+            _ => return None,
+        };
+        Some(Self {
+            stemmer: Stemmer::create(algorithm),
+        })
+    }
+
+    pub fn stem(&self, word: &str) -> String {
+        self.stemmer.stem(word).into_owned()
+    }
+
+    /// the shortest headwords sharing the stem of a single word `query`
+    ///
+    /// longer ones with the same stem are inflected or derived forms
+    /// themselves, e.g. "Hauser" for "Häusern"
+    pub fn lookup(
+        &self,
+        index: &HeadwordIndex,
+        query: &str,
+        limit: usize,
+        cancel: &Cancel,
+    ) -> Result<Vec<Location>> {
+        let lower = query.trim().to_lowercase();
+        let folded = index.fold(&lower);
+        if folded.chars().count() < 3 || folded.contains(char::is_whitespace) {
+            return Ok(Vec::new());
+        }
+
+        // the rules need the real spelling, the stem is folded like the keys
+        let stem = index.fold(&self.stem(&lower));
+        let stem_len = stem.chars().count();
+        if stem_len < 2 {
+            return Ok(Vec::new());
+        }
+
+        // stems aren't always a prefix of the lemma, "studies" -> "studi" <- "study"
+        let prefix: String = stem.chars().take(stem_len.max(3) - 1).collect();
+        let mut lemmas = index.search_prefix_where(
+            &prefix,
+            |key| key != folded && index.fold(&self.stem(key)) == stem,
+            cancel,
+        )?;
+        let key_len = |(headword, ..): &Location| index.fold(headword).chars().count();
+        if let Some(shortest) = lemmas.iter().map(key_len).min() {
+            lemmas.retain(|location| key_len(location) == shortest);
+        }
+        lemmas.truncate(limit);
+        Ok(lemmas)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::normalize::Normalizer;
+    use crate::dictionary::trie::PrefixTrie;
+
+    fn index(words: &[&str]) -> HeadwordIndex {
+        let mut trie = PrefixTrie::new();
+        for (i, word) in words.iter().enumerate() {
+            trie.insert(word, i as u64, 1);
+        }
+        HeadwordIndex::from_bytes(HeadwordIndex::encode(&trie), Normalizer::default()).unwrap()
+    }
+
+    fn lemmas(lemmatizer: &Lemmatizer, index: &HeadwordIndex, query: &str) -> Vec<String> {
+        lemmatizer
+            .lookup(index, query, 10, &Cancel::default())
+            .unwrap()
+            .into_iter()
+            .map(|(word, ..)| word)
+            .collect()
+    }
+
+    #[test]
+    fn test_finds_lemmas_of_inflected_words() {
+        let english = Lemmatizer::for_language("ENG").unwrap();
+        let index = index(&["run", "runner", "rung", "study", "student"]);
+        assert_eq!(lemmas(&english, &index, "running"), ["run"]);
+        assert_eq!(lemmas(&english, &index, "Studies"), ["study"]);
+        assert!(lemmas(&english, &index, "running shoes").is_empty());
+
+        let german = Lemmatizer::for_language("deu").unwrap();
+        let index = self::index(&["Haus", "Hausaufgabe", "Hauser"]);
+        assert_eq!(lemmas(&german, &index, "Häusern"), ["Haus"]);
+
+        let french = Lemmatizer::for_language("fra").unwrap();
+        let index = self::index(&["manger", "mangue", "mangeoire"]);
+        assert_eq!(lemmas(&french, &index, "mangé"), ["manger"]);
+
+        assert!(Lemmatizer::for_language("lat").is_none());
+    }
+}
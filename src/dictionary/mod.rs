@@ -1,71 +1,433 @@
+mod affix;
 mod cache;
+mod cancel;
+mod dictd;
+mod dictzip;
+mod files;
+mod frequency;
+mod fulltext;
+mod fuzzy;
+mod index;
+mod info;
+mod lemma;
 mod models;
+mod normalize;
+mod parser;
+mod pattern;
+mod source;
+mod stardict;
+mod table;
 mod trie;
 
-pub use cache::CacheManager;
-pub use models::DictEntry;
-pub use trie::PrefixTrie;
+pub use cache::{CacheManager, RebuildReason};
+pub use cancel::{Cancel, Cancelled};
+pub use dictd::DictdWriter;
+pub use dictzip::DictZipReader;
+pub use files::{DictFiles, DictFormat};
+pub use frequency::FrequencyList;
+pub use index::HeadwordIndex;
+pub use info::DictionaryInfo;
+pub use lemma::Lemmatizer;
+pub use models::{DictEntry, Hit, ParsedEntry, Sense};
+pub use normalize::Normalizer;
+pub use parser::{segments, Segment};
+pub use pattern::Pattern;
+pub use source::{DictionarySource, Hits};
 
-use anyhow::Result;
+use affix::{InfixIndex, SuffixIndex};
+use anyhow::{bail, Context, Result};
+use fulltext::FullTextIndex;
+use std::collections::HashSet;
 use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use trie::Location;
 
-const MAX_RESULTS: usize = 50;
+/// lemmas shown ahead of the prefix matches of an inflected query
+const LEMMA_LIMIT: usize = 10;
+/// how often a search waiting for an index build looks at its cancel token
+const BUILD_POLL: Duration = Duration::from_millis(50);
+
+type Built<T> = Result<(T, Option<RebuildReason>)>;
 
 pub struct Dictionary {
-    index: PrefixTrie,
-    data_content: String,
+    index: HeadwordIndex,
+    definitions: Arc<Definitions>,
+    files: DictFiles,
+    /// folds definition text, which is in the target language
+    text_normalizer: Normalizer,
+    /// None for source languages without stemming rules
+    lemmatizer: Option<Lemmatizer>,
+    fulltext: Background<FullTextIndex>,
+    suffixes: Background<SuffixIndex>,
+    infixes: Background<InfixIndex>,
+    /// caches rebuilt or files that failed to load since the last `take_notices`
+    notices: Mutex<Vec<String>>,
+}
+
+/// reads definition text, shared with the thread building the full-text index
+struct Definitions {
+    data: DictZipReader,
+    format: DictFormat,
+    /// StarDict field types shared by all entries, see `stardict::definition_text`
+    type_sequence: Option<String>,
+}
+
+/// a secondary index loaded or built once on its own thread
+///
+/// searches wait for it until a newer query cancels them, the build keeps
+/// going so the next search picks it up instead of starting over
+struct Background<T> {
+    table: OnceLock<T>,
+    /// the running build, None before the first search and after a failure
+    pending: Mutex<Option<Receiver<Built<T>>>>,
+}
+
+impl<T> Default for Background<T> {
+    fn default() -> Self {
+        Self {
+            table: OnceLock::new(),
+            pending: Mutex::new(None),
+        }
+    }
 }
 
 impl Dictionary {
-    pub fn new(index_path: &Path, dict_path: &Path) -> Result<Self> {
-        let index = CacheManager::load_or_build_trie(index_path)?;
-        let data_content = CacheManager::load_or_decompress_dict(dict_path)?;
+    pub fn new(
+        files: &DictFiles,
+        normalizer: Normalizer,
+        text_normalizer: Normalizer,
+        lemmatizer: Option<Lemmatizer>,
+        frequencies: Option<&Path>,
+    ) -> Result<Self> {
+        let (index, reason) = CacheManager::load_or_build_index(files, normalizer)?;
+        // a broken list only costs the ranking, not the dictionary
+        let mut notices = Vec::new();
+        let frequencies = match frequencies.map(|path| FrequencyList::load(path, normalizer)) {
+            Some(Ok(list)) => Some(list),
+            Some(Err(e)) => {
+                notices.push(format!("{:#}", e));
+                None
+            }
+            None => None,
+        };
+        let data = DictZipReader::open(&files.data)?;
+
+        let type_sequence = match &files.ifo {
+            Some(ifo) => stardict::Ifo::load(ifo)?
+                .type_sequence()
+                .map(str::to_string),
+            None => None,
+        };
+        if files.format == DictFormat::Dictd {
+            CacheManager::remove_legacy_caches(&files.index, &files.data);
+        }
+
+        let dictionary = Self {
+            index: index.with_frequencies(frequencies),
+            definitions: Arc::new(Definitions {
+                data,
+                format: files.format,
+                type_sequence,
+            }),
+            files: files.clone(),
+            text_normalizer,
+            lemmatizer,
+            fulltext: Background::default(),
+            suffixes: Background::default(),
+            infixes: Background::default(),
+            notices: Mutex::new(notices),
+        };
+        dictionary.note_rebuild("index", reason);
+        Ok(dictionary)
+    }
+
+    fn note_rebuild(&self, cache: &str, reason: Option<RebuildReason>) {
+        if let (Some(reason), Ok(mut notices)) = (reason, self.notices.lock()) {
+            notices.push(format!("rebuilt {} cache: {}", cache, reason));
+        }
+    }
+
+    /// shows the error as a notice, None when there was one. a cancelled
+    /// search isn't worth one
+    fn or_notice<T>(&self, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) if e.is::<Cancelled>() => None,
+            Err(e) => {
+                if let Ok(mut notices) = self.notices.lock() {
+                    notices.push(format!("{:#}", e));
+                }
+                None
+            }
+        }
+    }
+
+    /// the table in `cell`, loaded on first use by a build that `cancel`
+    /// doesn't stop, recording why it had to be rebuilt. failures aren't
+    /// kept, so the next search tries again
+    fn loaded<'a, T: Send + 'static>(
+        &self,
+        cell: &'a Background<T>,
+        cache: &str,
+        cancel: &Cancel,
+        load: impl FnOnce() -> Built<T> + Send + 'static,
+    ) -> Result<&'a T> {
+        if let Some(table) = cell.table.get() {
+            return Ok(table);
+        }
+
+        let mut pending = cell.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let receiver = pending.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let _ = sender.send(load());
+            });
+            receiver
+        });
+        loop {
+            match receiver.recv_timeout(BUILD_POLL) {
+                Ok(result) => {
+                    *pending = None;
+                    let (table, reason) =
+                        result.with_context(|| format!("Failed to load {} index", cache))?;
+                    self.note_rebuild(cache, reason);
+                    return Ok(cell.table.get_or_init(|| table));
+                }
+                Err(RecvTimeoutError::Timeout) if cancel.is_cancelled() => bail!(Cancelled),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    *pending = None;
+                    bail!("Failed to load {} index: the build stopped", cache);
+                }
+            }
+        }
+    }
+
+    fn fulltext(&self, cancel: &Cancel) -> Result<&FullTextIndex> {
+        let (files, index) = (self.files.clone(), self.index.clone());
+        let definitions = Arc::clone(&self.definitions);
+        let normalizer = self.text_normalizer;
+        self.loaded(&self.fulltext, "full-text", cancel, move || {
+            CacheManager::load_or_build_fulltext(
+                &files,
+                &index,
+                |offset, length| definitions.body(offset, length),
+                normalizer,
+            )
+        })
+    }
+
+    fn suffixes(&self, cancel: &Cancel) -> Result<&SuffixIndex> {
+        let (files, index) = (self.files.clone(), self.index.clone());
+        self.loaded(&self.suffixes, "suffix", cancel, move || {
+            CacheManager::load_or_build_suffix(&files, &index)
+        })
+    }
+
+    fn infixes(&self, cancel: &Cancel) -> Result<&InfixIndex> {
+        let (files, index) = (self.files.clone(), self.index.clone());
+        self.loaded(&self.infixes, "infix", cancel, move || {
+            CacheManager::load_or_build_infix(&files, &index)
+        })
+    }
+
+    /// hits of a search for `query`, the ones whose headword folds to it
+    /// are marked as exact
+    fn query_hits(
+        &self,
+        matches: impl IntoIterator<Item = Location, IntoIter: Send + 'static>,
+        query: &str,
+    ) -> Hits {
+        let index = self.index.clone();
+        let query = index.fold(query.trim());
+        Box::new(matches.into_iter().map(move |location| Hit {
+            exact: index.fold(&location.0) == query,
+            ..to_hit(location)
+        }))
+    }
+
+    fn to_entry(&self, (headword, offset, length): (String, u64, u64)) -> Option<DictEntry> {
+        self.definitions
+            .text(offset, length)
+            .map(|definition| DictEntry {
+                headword,
+                definition,
+                lemma: None,
+            })
+    }
+}
 
-        Ok(Self {
-            index,
-            data_content,
+impl Definitions {
+    fn text(&self, offset: u64, length: u64) -> Option<String> {
+        let bytes = self.data.read(offset, length).ok()?;
+        let text = match self.format {
+            DictFormat::Dictd => String::from_utf8_lossy(&bytes).into_owned(),
+            DictFormat::StarDict => {
+                stardict::definition_text(&bytes, self.type_sequence.as_deref())
+            }
+        };
+        Some(text.trim().to_string())
+    }
+
+    /// the text reverse search looks at, without the dictd headword line
+    fn body(&self, offset: u64, length: u64) -> Option<String> {
+        let text = self.text(offset, length)?;
+        Some(match self.format {
+            DictFormat::Dictd => fulltext::definition_body(&text).to_string(),
+            DictFormat::StarDict => text,
         })
     }
+}
 
-    pub fn lookup(&self, query: &str) -> Vec<DictEntry> {
+impl DictionarySource for Dictionary {
+    fn lookup(&self, query: &str, cancel: &Cancel) -> Hits {
         if query.is_empty() {
-            return Vec::new();
+            return to_hits(Vec::new());
         }
 
-        let matches = self.index.search_prefix(query, MAX_RESULTS);
+        let Some(matches) = self.or_notice(self.index.prefix_matches(query, cancel)) else {
+            return to_hits(Vec::new());
+        };
+        let exact = self.index.record_of(&self.index.fold(query)).is_some();
+        let Some(lemmatizer) = self.lemmatizer.as_ref().filter(|_| !exact) else {
+            return self.query_hits(matches, query);
+        };
 
-        matches
-            .into_iter()
-            .filter_map(|(headword, offset, length)| {
-                self.extract_definition(offset, length)
-                    .map(|definition| DictEntry {
-                        headword,
-                        definition,
-                    })
-            })
-            .collect()
+        // inflected forms have no headword of their own, their lemmas go first
+        let lemmas = lemmatizer.lookup(&self.index, query, LEMMA_LIMIT, cancel);
+        let Some(lemmas) = self.or_notice(lemmas) else {
+            return to_hits(Vec::new());
+        };
+        let seen: HashSet<Location> = lemmas.iter().cloned().collect();
+        let lemmas = lemmas.into_iter().map(|location| {
+            let mut hit = to_hit(location);
+            hit.lemma = Some(hit.headword.clone());
+            hit
+        });
+        let matches = matches.filter(move |location| !seen.contains(location));
+        Box::new(lemmas.chain(self.query_hits(matches, query)))
+    }
+
+    fn entry(&self, hit: &Hit) -> Option<DictEntry> {
+        let definition = self.definitions.text(hit.offset, hit.length)?;
+        Some(DictEntry {
+            headword: hit.headword.clone(),
+            definition,
+            lemma: hit.lemma.clone(),
+        })
+    }
+
+    fn lookup_fuzzy(&self, query: &str, cancel: &Cancel) -> Option<Hits> {
+        if query.is_empty() {
+            return Some(to_hits(Vec::new()));
+        }
+
+        let matches = self.or_notice(self.index.search_fuzzy(query, cancel));
+        Some(self.query_hits(matches.into_iter().flatten(), query))
     }
 
-    fn extract_definition(&self, offset: u64, length: u64) -> Option<String> {
-        let start = offset as usize;
-        let end = start.checked_add(length as usize)?;
+    fn lookup_pattern(&self, pattern: &Pattern, cancel: &Cancel) -> Option<Hits> {
+        let matches = self.or_notice(self.index.search_pattern(pattern, cancel));
+        Some(to_hits(matches.into_iter().flatten()))
+    }
 
-        if end <= self.data_content.len() {
-            self.data_content
-                .get(start..end)
-                .map(|s| s.trim().to_string())
-        } else {
-            None
+    fn lookup_suffix(&self, query: &str, cancel: &Cancel) -> Option<Hits> {
+        if query.is_empty() {
+            return Some(to_hits(Vec::new()));
         }
+
+        let Some(suffixes) = self.or_notice(self.suffixes(cancel)) else {
+            return Some(to_hits(Vec::new()));
+        };
+
+        let records = suffixes.search(&self.index.fold(query));
+        let matches = self.index.ranked_locations(records, query, cancel);
+        let Some(matches) = self.or_notice(matches) else {
+            return Some(to_hits(Vec::new()));
+        };
+        Some(self.query_hits(matches, query))
     }
 
-    #[allow(dead_code)]
-    pub fn entry_count(&self) -> usize {
+    fn lookup_infix(&self, query: &str, cancel: &Cancel) -> Option<Hits> {
+        if query.is_empty() {
+            return Some(to_hits(Vec::new()));
+        }
+
+        let Some(infixes) = self.or_notice(self.infixes(cancel)) else {
+            return Some(to_hits(Vec::new()));
+        };
+
+        let records = infixes.search(&self.index.fold(query), &self.index, cancel);
+        let matches =
+            records.and_then(|records| self.index.ranked_locations(records, query, cancel));
+        let Some(matches) = self.or_notice(matches) else {
+            return Some(to_hits(Vec::new()));
+        };
+        Some(self.query_hits(matches, query))
+    }
+
+    fn lookup_definitions(&self, query: &str, cancel: &Cancel) -> Option<Hits> {
+        if query.trim().is_empty() {
+            return Some(to_hits(Vec::new()));
+        }
+
+        let Some(fulltext) = self.or_notice(self.fulltext(cancel)) else {
+            return Some(to_hits(Vec::new()));
+        };
+
+        let index = self.index.clone();
+        let matches = fulltext
+            .search(query)
+            .into_iter()
+            .filter_map(move |(record, n)| index.location(record as usize, n as usize));
+        Some(self.query_hits(matches, query))
+    }
+
+    fn entries(&self) -> Option<Box<dyn Iterator<Item = DictEntry> + '_>> {
+        let mut locations: Vec<_> = (0..self.index.len())
+            .flat_map(|record| self.index.locations(record))
+            .collect();
+        // aliases share the location of their entry, which is read only once
+        locations.sort_by_key(|(_, offset, length)| (*offset, *length));
+        locations.dedup_by_key(|(_, offset, length)| (*offset, *length));
+
+        Some(Box::new(
+            locations
+                .into_iter()
+                .filter_map(|location| self.to_entry(location)),
+        ))
+    }
+
+    fn info(&self) -> Result<DictionaryInfo> {
+        DictionaryInfo::load(&self.files)
+    }
+
+    fn entry_count(&self) -> usize {
         self.index.len()
     }
 
-    #[allow(dead_code)]
-    pub fn data_size(&self) -> usize {
-        self.data_content.len()
+    fn take_notices(&self) -> Vec<String> {
+        self.notices
+            .lock()
+            .map(|mut notices| std::mem::take(&mut *notices))
+            .unwrap_or_default()
     }
 }
+
+fn to_hit((headword, offset, length): Location) -> Hit {
+    Hit {
+        headword,
+        offset,
+        length,
+        lemma: None,
+        exact: false,
+    }
+}
+
+/// matches are cheap locations, definitions are read for the hits that get shown
+fn to_hits(matches: impl IntoIterator<Item = Location, IntoIter: Send + 'static>) -> Hits {
+    Box::new(matches.into_iter().map(to_hit))
+}
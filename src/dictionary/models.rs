@@ -1,5 +1,58 @@
+use super::parser;
+
 #[derive(Debug, Clone)]
 pub struct DictEntry {
     pub headword: String,
     pub definition: String,
+    /// set when the entry was found as the lemma of an inflected query
+    pub lemma: Option<String>,
+}
+
+/// a search result whose definition is only read once it's shown,
+/// see `DictionarySource::entry`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub headword: String,
+    /// where the backend finds the definition
+    pub offset: u64,
+    pub length: u64,
+    /// set when the entry was found as the lemma of an inflected query
+    pub lemma: Option<String>,
+    /// the headword folds to the query, merged results list these first
+    pub exact: bool,
+}
+
+impl DictEntry {
+    pub fn parse(&self) -> ParsedEntry {
+        parser::parse(&self.headword, &self.definition)
+    }
+}
+
+/// a definition split into its parts, see `parser::parse`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedEntry {
+    pub headword: String,
+    /// without the surrounding slashes
+    pub pronunciation: Option<String>,
+    /// e.g. "n, masc", without the angle brackets
+    pub part_of_speech: Option<String>,
+    pub senses: Vec<Sense>,
+    /// targets of `{cross-references}`, in order of appearance
+    pub links: Vec<String>,
+}
+
+impl ParsedEntry {
+    /// dictd text of the entry, see `parser::format`
+    pub fn to_definition(&self) -> String {
+        parser::format(self)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sense {
+    /// the number of a `1.` style sense, None for unnumbered entries
+    pub number: Option<usize>,
+    /// translations and notes, `{links}` are kept inline
+    pub lines: Vec<String>,
+    pub examples: Vec<String>,
 }
@@ -0,0 +1,125 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// bump when folding rules change so existing caches are rebuilt
+const RULES_VERSION: u64 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Folding {
+    Default,
+    /// dotted and dotless i are separate letters
+    Turkic,
+}
+
+/// folds headwords and queries into search keys
+///
+/// the same normalizer has to be used when building an index and when
+/// querying it, `tag` identifies the rules a cached index was built with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Normalizer {
+    folding: Folding,
+    strip_diacritics: bool,
+}
+
+impl Normalizer {
+    /// picks folding rules for a FreeDict language code like "deu" or "TUR"
+    pub fn for_language(lang: &str, strip_diacritics: bool) -> Self {
+        let folding = match lang.to_lowercase().as_str() {
+            "tur" | "aze" | "tat" | "kaz" => Folding::Turkic,
+            _ => Folding::Default,
+        };
+
+        Self {
+            folding,
+            strip_diacritics,
+        }
+    }
+
+    pub fn fold(&self, word: &str) -> String {
+        let mut lower = String::with_capacity(word.len());
+        for ch in word.chars() {
+            match (self.folding, ch) {
+                (Folding::Turkic, 'I') => lower.push('ı'),
+                (Folding::Turkic, 'İ') => lower.push('i'),
+                _ => lower.extend(ch.to_lowercase()),
+            }
+        }
+
+        let mut folded = String::with_capacity(lower.len());
+        if self.strip_diacritics {
+            for ch in lower.nfkd() {
+                if is_combining_mark(ch) {
+                    // the dot of a decomposed İ is already handled above
+                    continue;
+                }
+                push_folded(&mut folded, ch, true);
+            }
+        } else {
+            for ch in lower.nfkc() {
+                push_folded(&mut folded, ch, false);
+            }
+        }
+        folded
+    }
+
+    pub fn tag(&self) -> u64 {
+        let folding = match self.folding {
+            Folding::Default => 0,
+            Folding::Turkic => 1,
+        };
+        (RULES_VERSION << 16) | (folding << 8) | u64::from(self.strip_diacritics)
+    }
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self {
+            folding: Folding::Default,
+            strip_diacritics: true,
+        }
+    }
+}
+
+/// letters that neither lowercasing nor decomposition turn into plain ones
+fn push_folded(out: &mut String, ch: char, strip_diacritics: bool) {
+    match ch {
+        'ß' => out.push_str("ss"),
+        'æ' => out.push_str("ae"),
+        'œ' => out.push_str("oe"),
+        'ø' if strip_diacritics => out.push('o'),
+        'ł' if strip_diacritics => out.push('l'),
+        'đ' if strip_diacritics => out.push('d'),
+        _ => out.push(ch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_diacritics_and_expands_sharp_s() {
+        let normalizer = Normalizer::for_language("deu", true);
+        assert_eq!(normalizer.fold("Café"), "cafe");
+        assert_eq!(normalizer.fold("Straße"), "strasse");
+        assert_eq!(normalizer.fold("Häuser"), "hauser");
+    }
+
+    #[test]
+    fn test_keeps_diacritics_when_disabled() {
+        let normalizer = Normalizer::for_language("fra", false);
+        assert_eq!(normalizer.fold("Café"), "café");
+        assert_eq!(normalizer.fold("ﬁn"), "fin");
+    }
+
+    #[test]
+    fn test_turkic_dotted_and_dotless_i() {
+        let turkish = Normalizer::for_language("tur", true);
+        assert_eq!(turkish.fold("IŞIK"), "ısık");
+        assert_eq!(turkish.fold("İstanbul"), "istanbul");
+
+        let english = Normalizer::for_language("eng", true);
+        assert_eq!(english.fold("IŞIK"), "isik");
+        assert_ne!(turkish.tag(), english.tag());
+    }
+}
@@ -0,0 +1,277 @@
+use super::models::{ParsedEntry, Sense};
+use super::normalize::Normalizer;
+
+/// splits a FreeDict dictd definition into its parts
+///
+/// the first line usually repeats the headword, optionally followed by
+/// `/pronunciation/` and `<part of speech>`, and the body has numbered
+/// senses, quoted example lines and `{cross-references}`, e.g.
+///
+/// ```text
+/// Haus /haʊs/ <n, neut>
+/// 1. house
+///    "Das Haus ist alt." - The house is old.
+/// 2. home, see {Heim}
+/// ```
+pub fn parse(headword: &str, definition: &str) -> ParsedEntry {
+    let mut lines = definition.lines().map(str::trim).filter(|l| !l.is_empty());
+
+    let mut entry = ParsedEntry {
+        headword: headword.to_string(),
+        ..ParsedEntry::default()
+    };
+
+    if let Some(first) = lines.next() {
+        // StarDict bodies and some dictd entries start right away
+        let fold = |text: &str| Normalizer::default().fold(text);
+        if fold(first).starts_with(&fold(headword)) {
+            entry.pronunciation = enclosed(first, '/', '/');
+            entry.part_of_speech = enclosed(first, '<', '>');
+        } else {
+            add_line(&mut entry.senses, first);
+        }
+    }
+
+    for line in lines {
+        add_line(&mut entry.senses, line);
+    }
+
+    for line in entry
+        .senses
+        .iter()
+        .flat_map(|s| s.lines.iter().chain(&s.examples))
+    {
+        for link in links(line) {
+            if !entry.links.contains(&link) {
+                entry.links.push(link);
+            }
+        }
+    }
+
+    entry
+}
+
+/// the dictd text `parse` reads back into `entry`, used by the importers
+pub fn format(entry: &ParsedEntry) -> String {
+    let mut header = entry.headword.clone();
+    if let Some(pronunciation) = &entry.pronunciation {
+        header.push_str(&format!(" /{}/", pronunciation));
+    }
+    if let Some(pos) = &entry.part_of_speech {
+        header.push_str(&format!(" <{}>", pos));
+    }
+
+    let mut lines = vec![header];
+    for sense in &entry.senses {
+        let marker = match sense.number {
+            Some(number) => format!("{}. ", number),
+            None => String::new(),
+        };
+        let indent = " ".repeat(marker.len());
+
+        if sense.lines.is_empty() && !marker.is_empty() {
+            lines.push(marker.trim_end().to_string());
+        }
+        for (i, line) in sense.lines.iter().enumerate() {
+            let prefix = if i == 0 { &marker } else { &indent };
+            lines.push(format!("{}{}", prefix, line));
+        }
+        for example in &sense.examples {
+            lines.push(format!("{}{}", indent, example));
+        }
+    }
+    lines.join("\n")
+}
+
+fn add_line(senses: &mut Vec<Sense>, line: &str) {
+    if let Some((number, rest)) = sense_number(line) {
+        senses.push(Sense {
+            number: Some(number),
+            lines: if rest.is_empty() {
+                Vec::new()
+            } else {
+                vec![rest.to_string()]
+            },
+            examples: Vec::new(),
+        });
+        return;
+    }
+
+    if senses.is_empty() {
+        senses.push(Sense::default());
+    }
+    let sense = senses.last_mut().unwrap();
+    if is_example(line) {
+        sense.examples.push(line.to_string());
+    } else {
+        sense.lines.push(line.to_string());
+    }
+}
+
+/// `12. rest` -> (12, "rest")
+fn sense_number(line: &str) -> Option<(usize, &str)> {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 {
+        return None;
+    }
+    let rest = line[digits..].strip_prefix('.')?;
+    if !(rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some((line[..digits].parse().ok()?, rest.trim()))
+}
+
+fn is_example(line: &str) -> bool {
+    line.starts_with(['"', '„', '“', '«'])
+}
+
+fn enclosed(text: &str, open: char, close: char) -> Option<String> {
+    let start = text.find(open)? + open.len_utf8();
+    let end = start + text[start..].find(close)?;
+    let inner = text[start..end].trim();
+    (!inner.is_empty()).then(|| inner.to_string())
+}
+
+/// part of a definition line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    /// target of a `{cross-reference}`, without the braces
+    Link(&'a str),
+}
+
+/// splits `text` at its `{links}`
+///
+/// older FreeDict databases also use braces for gender and number
+/// markers like `{f}` or `{pl}`, those stay plain text
+pub fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut plain_start = 0;
+    let mut at = 0;
+
+    while let Some(open) = text[at..].find('{').map(|i| at + i) {
+        let Some(close) = text[open..].find('}').map(|i| open + i) else {
+            break;
+        };
+        let target = text[open + 1..close].trim();
+        if !target.is_empty() && !is_grammar_marker(target) {
+            if plain_start < open {
+                segments.push(Segment::Text(&text[plain_start..open]));
+            }
+            segments.push(Segment::Link(target));
+            plain_start = close + 1;
+        }
+        at = close + 1;
+    }
+
+    if plain_start < text.len() {
+        segments.push(Segment::Text(&text[plain_start..]));
+    }
+    segments
+}
+
+/// targets of every `{link}` in `text`
+pub fn links(text: &str) -> Vec<String> {
+    segments(text)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Link(target) => Some(target.to_string()),
+            Segment::Text(_) => None,
+        })
+        .collect()
+}
+
+fn is_grammar_marker(text: &str) -> bool {
+    text.split([',', ';', ' '])
+        .filter(|part| !part.is_empty())
+        .all(|part| {
+            matches!(
+                part.trim_end_matches('.'),
+                "m" | "f" | "n" | "nt" | "pl" | "sg" | "no" | "adj" | "adv" | "v" | "vt" | "vi"
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_numbered_senses() {
+        let entry = parse(
+            "Haus",
+            "Haus /haʊs/ <n, neut>\n\
+             1. house\n   \"Das Haus ist alt.\" - The house is old.\n\
+             2. home, see {Heim}\n   household\n",
+        );
+
+        assert_eq!(entry.pronunciation.as_deref(), Some("haʊs"));
+        assert_eq!(entry.part_of_speech.as_deref(), Some("n, neut"));
+        assert_eq!(entry.senses.len(), 2);
+        assert_eq!(entry.senses[0].number, Some(1));
+        assert_eq!(entry.senses[0].lines, vec!["house"]);
+        assert_eq!(
+            entry.senses[0].examples,
+            vec!["\"Das Haus ist alt.\" - The house is old."]
+        );
+        assert_eq!(entry.senses[1].lines, vec!["home, see {Heim}", "household"]);
+        assert_eq!(entry.links, vec!["Heim"]);
+    }
+
+    #[test]
+    fn test_parses_unnumbered_entry() {
+        let entry = parse("cat", "cat <n>\nKatze {f}; Kater {m}");
+
+        assert_eq!(entry.pronunciation, None);
+        assert_eq!(entry.part_of_speech.as_deref(), Some("n"));
+        assert_eq!(entry.senses.len(), 1);
+        assert_eq!(entry.senses[0].number, None);
+        assert_eq!(entry.senses[0].lines, vec!["Katze {f}; Kater {m}"]);
+        assert!(entry.links.is_empty());
+    }
+
+    #[test]
+    fn test_splits_links() {
+        assert_eq!(
+            segments("see {Heim} or {home}"),
+            vec![
+                Segment::Text("see "),
+                Segment::Link("Heim"),
+                Segment::Text(" or "),
+                Segment::Link("home"),
+            ]
+        );
+        assert_eq!(segments("Katze {f}"), vec![Segment::Text("Katze {f}")]);
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let entry = parse(
+            "Haus",
+            "Haus /haʊs/ <n, neut>\n1. house\n   \"Das Haus\" - the house\n2. home, see {Heim}",
+        );
+        assert_eq!(parse("Haus", &format(&entry)), entry);
+
+        let entry = parse("cat", "cat\nKatze {f}");
+        assert_eq!(format(&entry), "cat\nKatze {f}");
+    }
+
+    #[test]
+    fn test_keeps_body_without_header() {
+        let entry = parse("x", "1. first\n2. second");
+        assert_eq!(entry.senses.len(), 2);
+        assert_eq!(entry.senses[1].lines, vec!["second"]);
+
+        let entry = parse("speed", "km/h per hour /x/\nmph");
+        assert_eq!(entry.pronunciation, None);
+        assert_eq!(entry.senses[0].lines, vec!["km/h per hour /x/", "mph"]);
+
+        let entry = parse("slang", "colloquial <informal>");
+        assert_eq!(entry.part_of_speech, None);
+        assert_eq!(entry.senses[0].lines, vec!["colloquial <informal>"]);
+
+        let entry = parse("Straße", "STRASSE <f>\nstreet");
+        assert_eq!(entry.part_of_speech.as_deref(), Some("f"));
+        assert_eq!(entry.senses[0].lines, vec!["street"]);
+    }
+}
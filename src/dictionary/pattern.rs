@@ -0,0 +1,230 @@
+use super::cancel::Cancel;
+use super::normalize::Normalizer;
+use super::table::SortedTable;
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+/// headword pattern typed into the search box
+///
+/// `h?use` and `*tion` are wildcards, `/^un.*bar$/` is a regular expression
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard(String),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    /// `?`
+    One,
+    /// `*`
+    Any,
+}
+
+impl Pattern {
+    /// None for plain queries, the error is shown while a regex is incomplete
+    pub fn parse(query: &str) -> Option<Result<Self>> {
+        if let Some(body) = query.strip_prefix('/') {
+            let body = body.strip_suffix('/').unwrap_or(body);
+            if body.is_empty() {
+                return None;
+            }
+            let regex = RegexBuilder::new(body)
+                .case_insensitive(true)
+                .build()
+                .context("Invalid regular expression");
+            return Some(regex.map(Pattern::Regex));
+        }
+
+        if query.contains(['*', '?']) {
+            return Some(Ok(Pattern::Wildcard(query.to_string())));
+        }
+
+        None
+    }
+
+    /// record indices of keys matching the pattern
+    ///
+    /// only keys starting with the literal prefix of the pattern are tested,
+    /// patterns without one fall back to scanning every key
+    pub fn search(
+        &self,
+        table: &SortedTable,
+        normalizer: Normalizer,
+        headwords: impl Fn(usize) -> Vec<String>,
+        cancel: &Cancel,
+    ) -> Result<Vec<usize>> {
+        match self {
+            Pattern::Wildcard(pattern) => {
+                let tokens = wildcard_tokens(pattern, normalizer);
+                let prefix: String = tokens
+                    .iter()
+                    .map_while(|token| match token {
+                        Token::Literal(ch) => Some(*ch),
+                        _ => None,
+                    })
+                    .collect();
+
+                scan(table.prefix_range(prefix.as_bytes()), cancel, |i| {
+                    let key: Vec<char> = String::from_utf8_lossy(table.key(i)).chars().collect();
+                    wildcard_match(&tokens, &key)
+                })
+            }
+            Pattern::Regex(regex) => {
+                let prefix = normalizer.fold(&regex_literal_prefix(regex.as_str()));
+
+                scan(table.prefix_range(prefix.as_bytes()), cancel, |i| {
+                    // keys are folded, so also try the original spelling
+                    regex.is_match(&String::from_utf8_lossy(table.key(i)))
+                        || headwords(i).iter().any(|word| regex.is_match(word))
+                })
+            }
+        }
+    }
+}
+
+/// records of `range` passing `matches`, a range can span the whole table
+fn scan(
+    range: Range<usize>,
+    cancel: &Cancel,
+    matches: impl Fn(usize) -> bool,
+) -> Result<Vec<usize>> {
+    let mut records = Vec::new();
+    for (step, i) in range.enumerate() {
+        cancel.check(step)?;
+        if matches(i) {
+            records.push(i);
+        }
+    }
+    Ok(records)
+}
+
+fn wildcard_tokens(pattern: &str, normalizer: Normalizer) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+
+    let flush = |literal: &mut String, tokens: &mut Vec<Token>| {
+        tokens.extend(normalizer.fold(literal).chars().map(Token::Literal));
+        literal.clear();
+    };
+
+    for ch in pattern.chars() {
+        match ch {
+            '?' => {
+                flush(&mut literal, &mut tokens);
+                tokens.push(Token::One);
+            }
+            '*' => {
+                flush(&mut literal, &mut tokens);
+                tokens.push(Token::Any);
+            }
+            _ => literal.push(ch),
+        }
+    }
+    flush(&mut literal, &mut tokens);
+
+    tokens
+}
+
+/// glob matching with backtracking to the last `*`
+fn wildcard_match(tokens: &[Token], key: &[char]) -> bool {
+    let (mut t, mut k) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while k < key.len() {
+        match tokens.get(t) {
+            Some(Token::Any) => {
+                star = Some((t, k));
+                t += 1;
+            }
+            Some(Token::One) => {
+                t += 1;
+                k += 1;
+            }
+            Some(Token::Literal(ch)) if *ch == key[k] => {
+                t += 1;
+                k += 1;
+            }
+            _ => match star {
+                Some((star_t, star_k)) => {
+                    t = star_t + 1;
+                    k = star_k + 1;
+                    star = Some((star_t, star_k + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    tokens[t..].iter().all(|token| *token == Token::Any)
+}
+
+/// literal characters every match has to start with, e.g. "un" for `^un.*bar$`
+fn regex_literal_prefix(regex: &str) -> String {
+    let Some(body) = regex.strip_prefix('^') else {
+        return String::new();
+    };
+    // an alternation may start with anything
+    if body.contains('|') {
+        return String::new();
+    }
+
+    let chars: Vec<char> = body.chars().collect();
+    let mut prefix = String::new();
+    for (i, &ch) in chars.iter().enumerate() {
+        if !(ch.is_alphanumeric() || ch == ' ' || ch == '-') {
+            break;
+        }
+        // the last literal is optional or repeated when a quantifier follows
+        if matches!(chars.get(i + 1), Some('?' | '*' | '{')) {
+            break;
+        }
+        prefix.push(ch);
+    }
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, key: &str) -> bool {
+        let tokens = wildcard_tokens(pattern, Normalizer::default());
+        wildcard_match(&tokens, &key.chars().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("h?use", "house"));
+        assert!(!matches("h?use", "hose"));
+        assert!(matches("*tion", "nation"));
+        assert!(matches("*tion", "tion"));
+        assert!(!matches("*tion", "national"));
+        assert!(matches("a*b*c", "axxbyybc"));
+        assert!(matches("Caf?", "cafe"));
+    }
+
+    #[test]
+    fn test_regex_literal_prefix() {
+        assert_eq!(regex_literal_prefix("^un.*bar$"), "un");
+        assert_eq!(regex_literal_prefix("^hou?se"), "ho");
+        assert_eq!(regex_literal_prefix("^a|b"), "");
+        assert_eq!(regex_literal_prefix("house"), "");
+    }
+
+    #[test]
+    fn test_detects_query_syntax() {
+        assert!(Pattern::parse("house").is_none());
+        assert!(matches!(
+            Pattern::parse("h?use"),
+            Some(Ok(Pattern::Wildcard(_)))
+        ));
+        assert!(matches!(
+            Pattern::parse("/^un.*bar$/"),
+            Some(Ok(Pattern::Regex(_)))
+        ));
+        assert!(matches!(Pattern::parse("/(un"), Some(Err(_))));
+    }
+}
@@ -0,0 +1,60 @@
+use super::cancel::Cancel;
+use super::info::DictionaryInfo;
+use super::models::{DictEntry, Hit};
+use super::pattern::Pattern;
+use anyhow::Result;
+
+/// every hit of a search in rank order, the caller reads as many as it shows
+pub type Hits = Box<dyn Iterator<Item = Hit> + Send>;
+
+/// a searchable dictionary, the UI only talks to backends through this
+///
+/// optional searches return None when the backend doesn't support them,
+/// as opposed to an empty result list. searches can scan many headwords,
+/// so they give up with no hits once `cancel` says a newer query arrived
+pub trait DictionarySource: Send + Sync {
+    /// headwords starting with `query`, exact matches first
+    fn lookup(&self, query: &str, cancel: &Cancel) -> Hits;
+
+    /// the definition of a hit returned by one of the searches
+    fn entry(&self, hit: &Hit) -> Option<DictEntry>;
+
+    /// typo tolerant lookup of whole headwords
+    fn lookup_fuzzy(&self, _query: &str, _cancel: &Cancel) -> Option<Hits> {
+        None
+    }
+
+    /// headwords matching a wildcard or regex pattern
+    fn lookup_pattern(&self, _pattern: &Pattern, _cancel: &Cancel) -> Option<Hits> {
+        None
+    }
+
+    /// headwords ending with `query`
+    fn lookup_suffix(&self, _query: &str, _cancel: &Cancel) -> Option<Hits> {
+        None
+    }
+
+    /// headwords containing `query` anywhere
+    fn lookup_infix(&self, _query: &str, _cancel: &Cancel) -> Option<Hits> {
+        None
+    }
+
+    /// reverse lookup of headwords whose definition mentions `query`
+    fn lookup_definitions(&self, _query: &str, _cancel: &Cancel) -> Option<Hits> {
+        None
+    }
+
+    /// every entry once in file order, e.g. to derive another dictionary
+    fn entries(&self) -> Option<Box<dyn Iterator<Item = DictEntry> + '_>> {
+        None
+    }
+
+    fn info(&self) -> Result<DictionaryInfo>;
+
+    fn entry_count(&self) -> usize;
+
+    /// messages for the user, e.g. about caches rebuilt while loading or searching
+    fn take_notices(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,338 @@
+use super::files::DictFiles;
+use super::normalize::Normalizer;
+use super::trie::PrefixTrie;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const IFO_MAGIC: &str = "StarDict's dict ifo file";
+
+/// key=value pairs of a StarDict .ifo file
+#[derive(Debug, Clone, Default)]
+pub struct Ifo {
+    fields: HashMap<String, String>,
+}
+
+impl Ifo {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read ifo file: {:?}", path))?;
+        Self::parse(&text)
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines();
+        if lines
+            .next()
+            .map(|l| l.trim_start_matches('\u{feff}').trim())
+            != Some(IFO_MAGIC)
+        {
+            bail!("Not a StarDict ifo file");
+        }
+
+        let fields: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        if !fields.contains_key("bookname") {
+            bail!("StarDict ifo file has no bookname");
+        }
+
+        Ok(Self { fields })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .get(key)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// field types shared by every entry, e.g. "m" for plain text
+    pub fn type_sequence(&self) -> Option<&str> {
+        self.get("sametypesequence")
+    }
+
+    fn offset_bits(&self) -> u32 {
+        match self.get("idxoffsetbits") {
+            Some("64") => 64,
+            _ => 32,
+        }
+    }
+}
+
+/// builds the headword trie from the .idx and the .syn synonyms
+pub fn build_trie(files: &DictFiles, normalizer: Normalizer) -> Result<PrefixTrie> {
+    let ifo_path = files
+        .ifo
+        .as_ref()
+        .ok_or_else(|| anyhow!("StarDict dictionary without .ifo file"))?;
+    let ifo = Ifo::load(ifo_path)?;
+
+    let idx = read_maybe_gzipped(&files.index)?;
+    let entries = parse_idx(&idx, ifo.offset_bits())?;
+
+    let mut trie = PrefixTrie::with_normalizer(normalizer);
+    for (word, offset, size) in &entries {
+        trie.insert(word, *offset, *size);
+    }
+
+    if let Some(syn_path) = &files.synonyms {
+        let syn = fs::read(syn_path)
+            .with_context(|| format!("Failed to read synonym file: {:?}", syn_path))?;
+        for (word, target) in parse_syn(&syn)? {
+            if let Some((_, offset, size)) = entries.get(target) {
+                trie.insert(&word, *offset, *size);
+            }
+        }
+    }
+
+    Ok(trie)
+}
+
+fn read_maybe_gzipped(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    if !path.to_string_lossy().ends_with(".gz") {
+        return Ok(bytes);
+    }
+
+    let mut content = Vec::new();
+    flate2::read::GzDecoder::new(&bytes[..])
+        .read_to_end(&mut content)
+        .with_context(|| format!("Failed to decompress {:?}", path))?;
+    Ok(content)
+}
+
+/// word\0 | offset u32/u64 | size u32, big endian
+fn parse_idx(bytes: &[u8], offset_bits: u32) -> Result<Vec<(String, u64, u64)>> {
+    let offset_len = if offset_bits == 64 { 8 } else { 4 };
+    let mut entries = Vec::new();
+    let mut rest = bytes;
+
+    while !rest.is_empty() {
+        let (word, after) = split_word(rest)?;
+        if after.len() < offset_len + 4 {
+            bail!("Truncated StarDict index entry {:?}", word);
+        }
+
+        let offset = if offset_len == 8 {
+            u64::from_be_bytes(after[0..8].try_into().unwrap())
+        } else {
+            u64::from(u32::from_be_bytes(after[0..4].try_into().unwrap()))
+        };
+        let size = u32::from_be_bytes(after[offset_len..offset_len + 4].try_into().unwrap());
+
+        entries.push((word, offset, u64::from(size)));
+        rest = &after[offset_len + 4..];
+    }
+
+    Ok(entries)
+}
+
+/// word\0 | index into the .idx entries u32, big endian
+fn parse_syn(bytes: &[u8]) -> Result<Vec<(String, usize)>> {
+    let mut synonyms = Vec::new();
+    let mut rest = bytes;
+
+    while !rest.is_empty() {
+        let (word, after) = split_word(rest)?;
+        if after.len() < 4 {
+            bail!("Truncated StarDict synonym entry {:?}", word);
+        }
+        let target = u32::from_be_bytes(after[0..4].try_into().unwrap());
+        synonyms.push((word, target as usize));
+        rest = &after[4..];
+    }
+
+    Ok(synonyms)
+}
+
+fn split_word(bytes: &[u8]) -> Result<(String, &[u8])> {
+    let end = bytes
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| anyhow!("Unterminated StarDict headword"))?;
+    Ok((
+        String::from_utf8_lossy(&bytes[..end]).into_owned(),
+        &bytes[end + 1..],
+    ))
+}
+
+/// readable text of an entry
+///
+/// with a `sametypesequence` the type characters are omitted from the data,
+/// otherwise every field starts with its type. lowercase types are
+/// zero-terminated text, uppercase ones are binary data with a u32 size
+pub fn definition_text(bytes: &[u8], type_sequence: Option<&str>) -> String {
+    let mut fields = Vec::new();
+    let mut rest = bytes;
+
+    let mut next_field = |kind: char, rest: &mut &[u8], last: bool| {
+        if kind.is_ascii_lowercase() {
+            // the last field of a sametypesequence has no terminator
+            let end = if last {
+                rest.len()
+            } else {
+                rest.iter().position(|&b| b == 0).unwrap_or(rest.len())
+            };
+            let text = String::from_utf8_lossy(&rest[..end]).into_owned();
+            *rest = rest.get(end + 1..).unwrap_or_default();
+            fields.push(field_text(kind, &text));
+        } else {
+            // binary resources like sounds or images can't be shown
+            let size = if last || rest.len() < 4 {
+                rest.len()
+            } else {
+                let size = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+                *rest = &rest[4..];
+                size.min(rest.len())
+            };
+            *rest = &rest[size..];
+        }
+    };
+
+    match type_sequence {
+        Some(types) => {
+            let types: Vec<char> = types.chars().collect();
+            for (i, &kind) in types.iter().enumerate() {
+                if rest.is_empty() {
+                    break;
+                }
+                next_field(kind, &mut rest, i == types.len() - 1);
+            }
+        }
+        None => {
+            while let Some((&kind, after)) = rest.split_first() {
+                rest = after;
+                next_field(char::from(kind), &mut rest, false);
+            }
+        }
+    }
+
+    fields
+        .into_iter()
+        .filter(|f| !f.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// markup types are reduced to plain text
+fn field_text(kind: char, text: &str) -> String {
+    match kind {
+        'h' | 'g' | 'x' => strip_markup(text),
+        _ => text.to_string(),
+    }
+}
+
+fn strip_markup(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        plain.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + len].trim().to_lowercase();
+        if tag.starts_with("br") || tag == "/p" || tag == "/div" || tag == "/li" {
+            plain.push('\n');
+        }
+        rest = &rest[start + len + 1..];
+    }
+    plain.push_str(rest);
+
+    plain
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::files::DictFormat;
+
+    fn idx_entry(word: &str, offset: u32, size: u32) -> Vec<u8> {
+        let mut entry = word.as_bytes().to_vec();
+        entry.push(0);
+        entry.extend_from_slice(&offset.to_be_bytes());
+        entry.extend_from_slice(&size.to_be_bytes());
+        entry
+    }
+
+    #[test]
+    fn test_parses_index_and_synonyms() {
+        let mut idx = idx_entry("apple", 0, 5);
+        idx.extend(idx_entry("house", 5, 4));
+        let entries = parse_idx(&idx, 32).unwrap();
+        assert_eq!(
+            entries,
+            vec![("apple".to_string(), 0, 5), ("house".to_string(), 5, 4)]
+        );
+
+        let mut syn = b"home\0".to_vec();
+        syn.extend_from_slice(&1u32.to_be_bytes());
+        assert_eq!(parse_syn(&syn).unwrap(), vec![("home".to_string(), 1)]);
+
+        assert!(parse_idx(&idx[..idx.len() - 2], 32).is_err());
+    }
+
+    #[test]
+    fn test_builds_trie_with_synonyms() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        fs::write(
+            dir.join("test.ifo"),
+            "StarDict's dict ifo file\nversion=2.4.2\nbookname=Test\nsametypesequence=m\n",
+        )
+        .unwrap();
+        fs::write(dir.join("test.dict"), "AppleHouse").unwrap();
+        let mut idx = idx_entry("Apfel", 0, 5);
+        idx.extend(idx_entry("Haus", 5, 5));
+        fs::write(dir.join("test.idx"), idx).unwrap();
+        let mut syn = b"Heim\0".to_vec();
+        syn.extend_from_slice(&1u32.to_be_bytes());
+        fs::write(dir.join("test.syn"), syn).unwrap();
+
+        let files = DictFiles::detect(dir).unwrap();
+        assert_eq!(files.format, DictFormat::StarDict);
+        let trie = build_trie(&files, Normalizer::default()).unwrap();
+        assert_eq!(
+            trie.search_prefix("heim", 10),
+            vec![("Heim".to_string(), 5, 5)]
+        );
+        assert_eq!(trie.len(), 3);
+    }
+
+    #[test]
+    fn test_definition_fields() {
+        assert_eq!(definition_text(b"Apfel", Some("m")), "Apfel");
+        assert_eq!(
+            definition_text(b"Haus<br>home &amp; house", Some("h")),
+            "Haus\nhome & house"
+        );
+        // type prefixed fields without a sametypesequence
+        assert_eq!(definition_text(b"t/hAus/\0mHaus\0", None), "/hAus/\nHaus");
+    }
+
+    #[test]
+    fn test_parses_ifo() {
+        let ifo = Ifo::parse(
+            "StarDict's dict ifo file\nversion=3.0.0\nbookname=Test\nidxoffsetbits=64\nsametypesequence=m\n",
+        )
+        .unwrap();
+        assert_eq!(ifo.get("bookname"), Some("Test"));
+        assert_eq!(ifo.type_sequence(), Some("m"));
+        assert_eq!(ifo.offset_bits(), 64);
+        assert!(Ifo::parse("bookname=Test").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+const HEADER_LEN: usize = 24;
+
+/// backing storage of a table, either mmapped from the cache or built in memory
+pub enum TableBytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for TableBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            TableBytes::Mapped(mmap) => mmap,
+            TableBytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// the table is intact but its tag differs, e.g. other normalization rules
+#[derive(Debug)]
+pub struct SettingsMismatch;
+
+impl fmt::Display for SettingsMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Table was built with different settings")
+    }
+}
+
+impl std::error::Error for SettingsMismatch {}
+
+/// immutable sorted key -> payload table that is queried in place
+///
+/// layout (little endian):
+///   magic [u8; 4] | version u32 | tag u64 | count u64
+///   count x u64 record offsets, relative to the record block
+///   records: key_len u32 | key | payload_len u32 | payload
+///
+/// `tag` is an opaque value chosen by the owner, e.g. the rules used to build keys.
+/// clones share the bytes, so iterators over the table can own one
+#[derive(Clone)]
+pub struct SortedTable {
+    bytes: Arc<TableBytes>,
+    base: usize,
+    count: usize,
+    records_start: usize,
+}
+
+impl SortedTable {
+    /// reads the table starting at byte `base` of `bytes`, e.g. behind a cache header
+    pub fn load(
+        bytes: TableBytes,
+        base: usize,
+        magic: &[u8; 4],
+        version: u32,
+        tag: u64,
+    ) -> Result<Self> {
+        let data = bytes.get(base..).unwrap_or_default();
+        if data.len() < HEADER_LEN || &data[0..4] != magic {
+            bail!("Invalid table header");
+        }
+        let found_version = read_u32(data, 4);
+        if found_version != version {
+            bail!(
+                "Table version {} does not match expected {}",
+                found_version,
+                version
+            );
+        }
+        if read_u64(data, 8) != tag {
+            bail!(SettingsMismatch);
+        }
+
+        let count = usize::try_from(read_u64(data, 16))?;
+        let records_start = count
+            .checked_mul(8)
+            .and_then(|n| n.checked_add(HEADER_LEN))
+            .filter(|&start| start <= data.len())
+            .context("Table offsets exceed file size")?;
+
+        Ok(Self {
+            bytes: Arc::new(bytes),
+            base,
+            count,
+            records_start,
+        })
+    }
+
+    /// encodes entries that are already sorted by key
+    pub fn encode<'a, I>(entries: I, magic: &[u8; 4], version: u32, tag: u64) -> Vec<u8>
+    where
+        I: IntoIterator<Item = (&'a [u8], &'a [u8])>,
+    {
+        let mut offsets = Vec::new();
+        let mut records = Vec::new();
+
+        for (key, payload) in entries {
+            offsets.push(records.len() as u64);
+            records.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            records.extend_from_slice(key);
+            records.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            records.extend_from_slice(payload);
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + offsets.len() * 8 + records.len());
+        bytes.extend_from_slice(magic);
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&tag.to_le_bytes());
+        bytes.extend_from_slice(&(offsets.len() as u64).to_le_bytes());
+        for offset in offsets {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+        bytes.extend_from_slice(&records);
+        bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// key of record `i`, empty if the record is corrupt
+    pub fn key(&self, i: usize) -> &[u8] {
+        self.record(i).map(|(key, _)| key).unwrap_or_default()
+    }
+
+    /// payload of record `i`, empty if the record is corrupt
+    pub fn payload(&self, i: usize) -> &[u8] {
+        self.record(i)
+            .map(|(_, payload)| payload)
+            .unwrap_or_default()
+    }
+
+    /// index of the first record whose key is >= `key`
+    pub fn lower_bound(&self, key: &[u8]) -> usize {
+        let (mut low, mut high) = (0, self.count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.key(mid) < key {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    /// the record whose key is `key`
+    pub fn find(&self, key: &[u8]) -> Option<usize> {
+        let i = self.lower_bound(key);
+        (i < self.count && self.key(i) == key).then_some(i)
+    }
+
+    /// records whose key starts with `prefix`
+    pub fn prefix_range(&self, prefix: &[u8]) -> Range<usize> {
+        let start = self.lower_bound(prefix);
+        let (mut low, mut high) = (start, self.count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.key(mid).starts_with(prefix) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        start..low
+    }
+
+    fn record(&self, i: usize) -> Option<(&[u8], &[u8])> {
+        if i >= self.count {
+            return None;
+        }
+        let data = &self.bytes[self.base..];
+        let relative = usize::try_from(read_u64(data, HEADER_LEN + i * 8)).ok()?;
+        let start = self.records_start.checked_add(relative)?;
+
+        // lengths come from the file, which isn't checksummed on every load
+        let (key, rest) = length_prefixed(data.get(start..)?)?;
+        let (payload, _) = length_prefixed(rest)?;
+        Some((key, payload))
+    }
+}
+
+/// splits `len u32 | bytes` off the front of `data`
+fn length_prefixed(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let len = read_u32(data.get(..4)?, 0) as usize;
+    let rest = &data[4..];
+    (len <= rest.len()).then(|| rest.split_at(len))
+}
+
+pub fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+pub fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
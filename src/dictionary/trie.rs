@@ -1,82 +1,80 @@
+use super::frequency::FrequencyList;
+use super::normalize::Normalizer;
 use qp_trie::Trie;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// (original headword, offset, length) of one dictionary entry
+pub type Location = (String, u64, u64);
 
 #[derive(Debug, Clone)]
 pub struct PrefixTrie {
-    trie: Trie<Vec<u8>, (u64, u64)>,
+    /// every location stored under a folded key, homographs share one key
+    trie: Trie<Vec<u8>, Vec<Location>>,
+    normalizer: Normalizer,
 }
 
-impl Serialize for PrefixTrie {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let entries: Vec<(Vec<u8>, (u64, u64))> =
-            self.trie.iter().map(|(k, v)| (k.to_vec(), *v)).collect();
-        entries.serialize(serializer)
+impl PrefixTrie {
+    pub fn new() -> Self {
+        Self::with_normalizer(Normalizer::default())
     }
-}
 
-impl<'de> Deserialize<'de> for PrefixTrie {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let entries: Vec<(Vec<u8>, (u64, u64))> = Vec::deserialize(deserializer)?;
-        let mut trie = Trie::new();
-        for (key, value) in entries {
-            trie.insert(key, value);
+    pub fn with_normalizer(normalizer: Normalizer) -> Self {
+        Self {
+            trie: Trie::new(),
+            normalizer,
         }
-        Ok(PrefixTrie { trie })
     }
-}
 
-impl PrefixTrie {
-    pub fn new() -> Self {
-        Self { trie: Trie::new() }
+    pub fn normalizer(&self) -> Normalizer {
+        self.normalizer
     }
 
     pub fn insert(&mut self, word: &str, offset: u64, length: u64) {
-        let key = word.to_lowercase().into_bytes();
-        self.trie.insert(key, (offset, length));
+        let key = self.normalizer.fold(word).into_bytes();
+        let location = (word.to_string(), offset, length);
+        match self.trie.get_mut(&key) {
+            Some(locations) => {
+                if !locations.contains(&location) {
+                    locations.push(location);
+                }
+            }
+            None => {
+                self.trie.insert(key, vec![location]);
+            }
+        }
     }
 
-    pub fn search_prefix(&self, prefix: &str, limit: usize) -> Vec<(String, u64, u64)> {
+    #[allow(dead_code)]
+    pub fn search_prefix(&self, prefix: &str, limit: usize) -> Vec<Location> {
         if prefix.is_empty() {
             return Vec::new();
         }
 
-        let prefix_lower = prefix.to_lowercase();
+        let prefix_lower = self.normalizer.fold(prefix);
         let prefix_bytes = prefix_lower.as_bytes();
 
-        let mut results: Vec<(String, u64, u64)> = self
+        let mut results: Vec<(String, Location)> = self
             .trie
             .iter_prefix(prefix_bytes)
-            .map(|(key, &(offset, length))| {
-                let word = String::from_utf8_lossy(key).into_owned();
-                (word, offset, length)
+            .flat_map(|(key, locations)| {
+                let key = String::from_utf8_lossy(key).into_owned();
+                locations
+                    .iter()
+                    .map(move |location| (key.clone(), location.clone()))
             })
             .collect();
 
-        results.sort_by(|a, b| {
-            let a_exact = a.0 == prefix_lower;
-            let b_exact = b.0 == prefix_lower;
-
-            if a_exact && !b_exact {
-                return std::cmp::Ordering::Less;
-            }
-            if !a_exact && b_exact {
-                return std::cmp::Ordering::Greater;
-            }
-
-            match a.0.len().cmp(&b.0.len()) {
-                std::cmp::Ordering::Equal => a.0.cmp(&b.0),
-                other => other,
-            }
-        });
-
-        results.truncate(limit);
+        rank_prefix_matches(&mut results, &prefix_lower, None);
         results
+            .into_iter()
+            .take(limit)
+            .map(|(_, location)| location)
+            .collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[Location])> {
+        self.trie
+            .iter()
+            .map(|(key, locations)| (key.as_slice(), locations.as_slice()))
     }
 
     #[allow(dead_code)]
@@ -90,6 +88,18 @@ impl PrefixTrie {
     }
 }
 
+/// sorts (folded key, match) pairs: exact match first, then more frequent
+/// words when there is a frequency list, then shorter keys, then alphabetical
+pub fn rank_prefix_matches<T>(
+    results: &mut [(String, T)],
+    prefix_lower: &str,
+    frequencies: Option<&FrequencyList>,
+) {
+    // words missing from the list come after all listed ones
+    let rank = |key: &str| frequencies.map_or(0, |list| list.rank(key).unwrap_or(u32::MAX));
+    results.sort_by_cached_key(|(key, _)| (key != prefix_lower, rank(key), key.len(), key.clone()));
+}
+
 impl Default for PrefixTrie {
     fn default() -> Self {
         Self::new()
@@ -120,7 +130,7 @@ mod tests {
 
         let results = trie.search_prefix("hel", 10);
         assert_eq!(results.len(), 1);
-        assert_eq!(results[0].0, "hello");
+        assert_eq!(results[0].0, "Hello");
     }
 
     #[test]
@@ -134,6 +144,19 @@ mod tests {
         assert_eq!(results[0].0, "test"); // Exact match first
     }
 
+    #[test]
+    fn test_homographs_keep_all_locations() {
+        let mut trie = PrefixTrie::new();
+        trie.insert("Bank", 0, 10);
+        trie.insert("bank", 10, 12);
+        trie.insert("bank", 10, 12);
+
+        let results = trie.search_prefix("bank", 10);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], ("Bank".to_string(), 0, 10));
+        assert_eq!(results[1], ("bank".to_string(), 10, 12));
+    }
+
     #[test]
     fn test_limit() {
         let mut trie = PrefixTrie::new();
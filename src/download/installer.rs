@@ -1,4 +1,5 @@
 use super::api::FreeDictEntry;
+use crate::dictionary::DictFiles;
 use anyhow::{anyhow, Context, Result};
 use std::fs::{self, File};
 use std::io::{Read, Write};
@@ -53,54 +54,26 @@ pub fn extract_tar_xz(tar_path: &Path, output_dir: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn find_dict_files(dict_dir: &Path) -> Result<(PathBuf, PathBuf)> {
-    let mut index_path = None;
-    let mut dict_path = None;
-
-    for entry in fs::read_dir(dict_dir).context("Failed to read dictionary directory")? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_file() {
-            let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
-            if filename.ends_with(".index") {
-                index_path = Some(path.clone());
-            } else if filename.ends_with(".dict.dz") {
-                dict_path = Some(path.clone());
-            }
-        }
+/// finds the dictionary files in `dict_dir` or one of its subdirectories
+/// and detects whether they are dictd or StarDict
+pub fn find_dict_files(dict_dir: &Path) -> Result<DictFiles> {
+    if let Some(files) = DictFiles::detect(dict_dir) {
+        return Ok(files);
     }
 
-    // check subdirectories
-    if index_path.is_none() || dict_path.is_none() {
-        for entry in fs::read_dir(dict_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if path.is_dir() {
-                for subentry in fs::read_dir(&path)? {
-                    let subentry = subentry?;
-                    let subpath = subentry.path();
-
-                    if subpath.is_file() {
-                        let filename = subpath.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
-                        if filename.ends_with(".index") && index_path.is_none() {
-                            index_path = Some(subpath.clone());
-                        } else if filename.ends_with(".dict.dz") && dict_path.is_none() {
-                            dict_path = Some(subpath.clone());
-                        }
-                    }
-                }
+    // archives usually unpack into a subdirectory
+    for entry in fs::read_dir(dict_dir).context("Failed to read dictionary directory")? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if let Some(files) = DictFiles::detect(&path) {
+                return Ok(files);
             }
         }
     }
 
-    match (index_path, dict_path) {
-        (Some(index), Some(dict)) => Ok((index, dict)),
-        _ => Err(anyhow!("Could not find .index or .dict.dz files")),
-    }
+    Err(anyhow!(
+        "Could not find dictd (.index/.dict.dz) or StarDict (.ifo/.idx/.dict) files"
+    ))
 }
 
 pub fn download_and_install<F>(
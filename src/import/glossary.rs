@@ -0,0 +1,279 @@
+use crate::dictionary::DictdWriter;
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// what a glossary column contains
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Headword,
+    Definition,
+    PartOfSpeech,
+    Notes,
+    Ignore,
+}
+
+impl Column {
+    /// header names and `--columns` values, `-` skips a column
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "headword" | "word" | "term" | "lemma" | "entry" => Some(Column::Headword),
+            "definition" | "translation" | "meaning" | "gloss" | "description" => {
+                Some(Column::Definition)
+            }
+            "pos" | "part of speech" | "part_of_speech" | "type" => Some(Column::PartOfSpeech),
+            "notes" | "note" | "comment" | "comments" | "remarks" => Some(Column::Notes),
+            "-" | "" | "ignore" => Some(Column::Ignore),
+            _ => None,
+        }
+    }
+}
+
+/// columns of a glossary without a header row
+const DEFAULT_COLUMNS: [Column; 4] = [
+    Column::Headword,
+    Column::Definition,
+    Column::PartOfSpeech,
+    Column::Notes,
+];
+
+/// how to read a glossary, anything left as None is detected
+#[derive(Debug, Clone, Default)]
+pub struct GlossaryOptions {
+    pub delimiter: Option<u8>,
+    pub columns: Option<Vec<Column>>,
+    pub has_header: Option<bool>,
+}
+
+/// `tab`, `comma`, `semicolon`, `pipe` or a single character
+pub fn parse_delimiter(value: &str) -> Result<u8> {
+    match value {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        "comma" => Ok(b','),
+        "semicolon" => Ok(b';'),
+        "pipe" => Ok(b'|'),
+        _ if value.len() == 1 => Ok(value.as_bytes()[0]),
+        _ => bail!("Invalid delimiter {:?}", value),
+    }
+}
+
+/// comma separated column roles, e.g. `headword,definition,-,notes`
+pub fn parse_columns(value: &str) -> Result<Vec<Column>> {
+    value
+        .split(',')
+        .map(|name| Column::parse(name).ok_or_else(|| anyhow!("Unknown column {:?}", name)))
+        .collect()
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Row {
+    headword: String,
+    definition: String,
+    part_of_speech: String,
+    notes: String,
+}
+
+/// the entries of a TSV or CSV glossary and their number
+pub(super) fn read_glossary(
+    path: &Path,
+    options: &GlossaryOptions,
+) -> Result<(DictdWriter, usize)> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read glossary: {:?}", path))?;
+    let entries = group_rows(parse_rows(text.trim_start_matches('\u{feff}'), options)?);
+
+    let mut writer = DictdWriter::new();
+    for (headword, rows) in &entries {
+        writer.add(headword, &entry_text(headword, rows));
+    }
+    Ok((writer, entries.len()))
+}
+
+fn parse_rows(text: &str, options: &GlossaryOptions) -> Result<Vec<Row>> {
+    let delimiter = options.delimiter.unwrap_or_else(|| detect_delimiter(text));
+
+    // quotes are common in definitions and rarely used for quoting in TSV
+    let mut records: Vec<Vec<String>> = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .quoting(delimiter != b'\t')
+        .from_reader(text.as_bytes())
+        .records()
+        .map(|record| Ok(record?.iter().map(|c| c.trim().to_string()).collect()))
+        .collect::<Result<_>>()
+        .context("Failed to parse glossary")?;
+
+    let header: Option<Vec<Column>> = records.first().and_then(|first| {
+        first
+            .iter()
+            .map(|cell| Column::parse(cell))
+            .collect::<Option<Vec<_>>>()
+            .filter(|columns| {
+                columns.contains(&Column::Headword) && columns.contains(&Column::Definition)
+            })
+    });
+    let has_header = options.has_header.unwrap_or(header.is_some());
+    if has_header && !records.is_empty() {
+        records.remove(0);
+    }
+
+    let columns = match (&options.columns, header) {
+        (Some(columns), _) => columns.clone(),
+        (None, Some(header)) if has_header => header,
+        _ => DEFAULT_COLUMNS.to_vec(),
+    };
+    if !columns.contains(&Column::Headword) || !columns.contains(&Column::Definition) {
+        bail!("Glossary columns need a headword and a definition");
+    }
+
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let mut row = Row::default();
+            for (column, cell) in columns.iter().zip(record) {
+                let field = match column {
+                    Column::Headword => &mut row.headword,
+                    Column::Definition => &mut row.definition,
+                    Column::PartOfSpeech => &mut row.part_of_speech,
+                    Column::Notes => &mut row.notes,
+                    Column::Ignore => continue,
+                };
+                *field = cell;
+            }
+            row
+        })
+        .filter(|row| !row.headword.is_empty() && !row.definition.is_empty())
+        .collect())
+}
+
+/// a tab anywhere in the first line wins, otherwise the most common separator
+fn detect_delimiter(text: &str) -> u8 {
+    let first = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    if first.contains('\t') {
+        return b'\t';
+    }
+
+    [b',', b';', b'|']
+        .into_iter()
+        .max_by_key(|&d| first.bytes().filter(|&b| b == d).count())
+        .filter(|&d| first.as_bytes().contains(&d))
+        .unwrap_or(b',')
+}
+
+/// rows sharing a headword become senses of one entry, in file order
+fn group_rows(rows: Vec<Row>) -> Vec<(String, Vec<Row>)> {
+    let mut groups: Vec<(String, Vec<Row>)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for row in rows {
+        match positions.get(&row.headword) {
+            Some(&i) => groups[i].1.push(row),
+            None => {
+                positions.insert(row.headword.clone(), groups.len());
+                groups.push((row.headword.clone(), vec![row]));
+            }
+        }
+    }
+    groups
+}
+
+/// FreeDict style entry text, so the parser picks up senses and part of speech
+fn entry_text(headword: &str, rows: &[Row]) -> String {
+    let shared_pos = rows
+        .iter()
+        .all(|row| row.part_of_speech == rows[0].part_of_speech)
+        .then_some(rows[0].part_of_speech.as_str())
+        .filter(|pos| !pos.is_empty());
+
+    let mut text = match shared_pos {
+        Some(pos) => format!("{} <{}>\n", headword, pos),
+        None => format!("{}\n", headword),
+    };
+    for (i, row) in rows.iter().enumerate() {
+        let mut line = String::new();
+        if rows.len() > 1 {
+            line.push_str(&format!("{}. ", i + 1));
+        }
+        if shared_pos.is_none() && !row.part_of_speech.is_empty() {
+            line.push_str(&format!("<{}> ", row.part_of_speech));
+        }
+        line.push_str(&row.definition);
+        text.push_str(&line);
+        text.push('\n');
+        if !row.notes.is_empty() {
+            text.push_str(&format!("Note: {}\n", row.notes));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::DictEntry;
+
+    #[test]
+    fn test_detects_delimiter_and_header() {
+        assert_eq!(detect_delimiter("word\tdefinition\n"), b'\t');
+        assert_eq!(detect_delimiter("a;b, c;d\n"), b';');
+        assert_eq!(detect_delimiter("single column\n"), b',');
+
+        let rows = parse_rows(
+            "Definition;Term;Notes\n\"house; home\";Haus;neuter\n",
+            &GlossaryOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![Row {
+                headword: "Haus".to_string(),
+                definition: "house; home".to_string(),
+                part_of_speech: String::new(),
+                notes: "neuter".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_configured_columns() {
+        let options = GlossaryOptions {
+            delimiter: Some(b'|'),
+            columns: Some(parse_columns("-,definition,headword").unwrap()),
+            has_header: Some(false),
+        };
+        let rows = parse_rows("1|apple|Apfel\n2|pear|Birne\n", &options).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].headword, "Birne");
+        assert_eq!(rows[1].definition, "pear");
+
+        assert!(parse_columns("headword,price").is_err());
+        assert_eq!(parse_delimiter("tab").unwrap(), b'\t');
+    }
+
+    #[test]
+    fn test_rows_become_freedict_entries() {
+        let rows = parse_rows(
+            "Rollout\tgradual release\tn\n\
+             Release\tpublished version\tn\n\
+             Rollout\tdeployment to all users\tn\tsee {Release}\n",
+            &GlossaryOptions::default(),
+        )
+        .unwrap();
+        let groups = group_rows(rows);
+        assert_eq!(groups.len(), 2);
+
+        let (headword, rows) = &groups[0];
+        let entry = DictEntry {
+            headword: headword.clone(),
+            definition: entry_text(headword, rows),
+            lemma: None,
+        }
+        .parse();
+        assert_eq!(entry.part_of_speech.as_deref(), Some("n"));
+        assert_eq!(entry.senses.len(), 2);
+        assert_eq!(entry.senses[1].lines[1], "Note: see {Release}");
+        assert_eq!(entry.links, vec!["Release".to_string()]);
+    }
+}
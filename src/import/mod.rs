@@ -0,0 +1,220 @@
+mod glossary;
+mod reverse;
+mod tei;
+
+pub use glossary::{parse_columns, parse_delimiter, GlossaryOptions};
+
+use crate::config::DictConfig;
+use crate::dictionary::{DictFormat, DictdWriter, DictionarySource};
+use anyhow::{bail, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// name and languages of an imported dictionary, detected when left as None
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// defaults to the file name
+    pub name: Option<String>,
+    pub from_lang: Option<String>,
+    pub to_lang: Option<String>,
+    pub glossary: GlossaryOptions,
+    /// replace an earlier import with the same id instead of failing
+    pub replace: bool,
+}
+
+/// id an imported file gets, the files are stored in `data_dir/<id>`
+pub fn import_id(path: &Path, options: &ImportOptions) -> String {
+    let name = import_name(path, options);
+    match slug(&name) {
+        // e.g. names in other scripts, the hash keeps them apart
+        slug if slug.is_empty() => format!("import-{:08x}", crc32fast::hash(name.as_bytes())),
+        slug => format!("import-{}", slug),
+    }
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "import".to_string())
+}
+
+fn import_name(path: &Path, options: &ImportOptions) -> String {
+    options.name.clone().unwrap_or_else(|| file_stem(path))
+}
+
+/// compiles a TEI file or a TSV/CSV glossary into a dictd dictionary
+/// below `data_dir`, returns its config and number of entries
+pub fn import_file(
+    path: &Path,
+    options: &ImportOptions,
+    data_dir: &Path,
+) -> Result<(DictConfig, usize)> {
+    let id = import_id(path, options);
+    if !options.replace && data_dir.join(&id).exists() {
+        bail!(
+            "A dictionary named {:?} was already imported",
+            import_name(path, options)
+        );
+    }
+
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let (writer, count, title) = match extension.as_str() {
+        "tei" | "xml" => tei::read_tei(path)?,
+        _ => {
+            let (writer, count) = glossary::read_glossary(path, &options.glossary)?;
+            (writer, count, None)
+        }
+    };
+    if count == 0 {
+        bail!("No entries found in {:?}", path);
+    }
+
+    let stem = file_stem(path);
+    let name = import_name(path, options);
+
+    let source = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = write_dictionary(
+        writer,
+        &id,
+        title.as_deref().unwrap_or(&name),
+        &format!("Imported from {}", source.display()),
+        data_dir,
+    )?;
+
+    // FreeDict names its files after the language pair, e.g. eng-deu.tei
+    let (from_lang, to_lang) = match stem.split_once('-') {
+        Some((from, to)) if is_language_code(from) && is_language_code(to) => {
+            (from.to_uppercase(), to.to_uppercase())
+        }
+        _ => ("UNK".to_string(), "UNK".to_string()),
+    };
+
+    let dict_config = DictConfig {
+        id,
+        name,
+        from_lang: options.from_lang.clone().unwrap_or(from_lang),
+        to_lang: options.to_lang.clone().unwrap_or(to_lang),
+        path: dir,
+        active: true,
+        strip_diacritics: true,
+        format: DictFormat::Dictd,
+        origin: None,
+    };
+    Ok((dict_config, count))
+}
+
+/// derives a dictionary in the opposite direction from the translations
+/// in `source`, the dictionary configured as `original`
+pub fn build_reverse(
+    source: &dyn DictionarySource,
+    original: &DictConfig,
+    data_dir: &Path,
+) -> Result<(DictConfig, usize)> {
+    let (writer, count) = reverse::read_reverse(source)?;
+    if count == 0 {
+        bail!("No translations found in {}", original.name);
+    }
+
+    let id = format!("reverse-{}", original.id);
+    let name = format!("{} (reverse)", original.name);
+    let dir = write_dictionary(
+        writer,
+        &id,
+        &name,
+        &format!("Derived from the translations in {}", original.name),
+        data_dir,
+    )?;
+
+    let dict_config = DictConfig {
+        id,
+        name,
+        from_lang: original.to_lang.clone(),
+        to_lang: original.from_lang.clone(),
+        path: dir,
+        active: true,
+        strip_diacritics: original.strip_diacritics,
+        format: DictFormat::Dictd,
+        origin: Some(original.id.clone()),
+    };
+    Ok((dict_config, count))
+}
+
+/// adds the metadata shown in the info panel and writes the files to `data_dir/<id>`
+fn write_dictionary(
+    mut writer: DictdWriter,
+    id: &str,
+    short: &str,
+    info: &str,
+    data_dir: &Path,
+) -> Result<PathBuf> {
+    writer.add_metadata("short", short);
+    writer.add_metadata("info", info);
+    writer.add_metadata("utf8", "");
+
+    let dir = data_dir.join(id);
+    writer.write(&dir, id)?;
+    Ok(dir)
+}
+
+fn is_language_code(code: &str) -> bool {
+    (2..=3).contains(&code.len()) && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// lowercase ascii letters and digits joined by dashes
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::{DictFiles, DictionaryInfo};
+
+    #[test]
+    fn test_imports_dictionary() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        let path = dir.join("Team Terms.csv");
+        fs::write(&path, "term,definition\nRollout,gradual release\n").unwrap();
+
+        let (config, count) =
+            import_file(&path, &ImportOptions::default(), &dir.join("data")).unwrap();
+        assert_eq!(config.id, "import-team-terms");
+        assert_eq!(config.name, "Team Terms");
+        assert_eq!(config.from_lang, "UNK");
+        assert_eq!(count, 1);
+        assert!(import_file(&path, &ImportOptions::default(), &dir.join("data")).is_err());
+        let replace = ImportOptions {
+            replace: true,
+            ..ImportOptions::default()
+        };
+        assert!(import_file(&path, &replace, &dir.join("data")).is_ok());
+
+        let files = DictFiles::detect(&config.path).unwrap();
+        let info = DictionaryInfo::load(&files).unwrap();
+        assert_eq!(info.title.as_deref(), Some("Team Terms"));
+        assert!(info.utf8);
+    }
+
+    #[test]
+    fn test_ids_of_names_without_ascii() {
+        let id = |name: &str| {
+            let options = ImportOptions {
+                name: Some(name.to_string()),
+                ..ImportOptions::default()
+            };
+            import_id(Path::new("words.csv"), &options)
+        };
+        assert_eq!(id("Team Terms"), "import-team-terms");
+        assert!(id("日本語").len() > "import-".len());
+        assert_ne!(id("日本語"), id("Русский словарь"));
+    }
+}
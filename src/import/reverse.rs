@@ -0,0 +1,217 @@
+use crate::dictionary::{DictEntry, DictdWriter, DictionarySource, ParsedEntry, Sense};
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// longer translations are explanations rather than headwords
+const MAX_WORDS: usize = 4;
+
+/// an original headword a translation came from, with its part of speech
+#[derive(Debug, PartialEq)]
+struct Origin {
+    headword: String,
+    part_of_speech: Option<String>,
+}
+
+/// the translations of every entry in `source` as headwords, each
+/// linking back to the entries it was found in
+pub(super) fn read_reverse(source: &dyn DictionarySource) -> Result<(DictdWriter, usize)> {
+    let Some(entries) = source.entries() else {
+        bail!("This dictionary can't list its entries");
+    };
+    let groups = group_translations(entries);
+
+    let mut writer = DictdWriter::new();
+    for (translation, origins) in &groups {
+        writer.add(translation, &entry_text(translation, origins));
+    }
+    Ok((writer, groups.len()))
+}
+
+/// translations in order of first appearance, spellings differing
+/// only in case share an entry
+fn group_translations(entries: impl Iterator<Item = DictEntry>) -> Vec<(String, Vec<Origin>)> {
+    let mut groups: Vec<(String, Vec<Origin>)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for entry in entries {
+        let parsed = entry.parse();
+        for translation in translations(&parsed) {
+            let i = *positions
+                .entry(translation.to_lowercase())
+                .or_insert_with(|| {
+                    groups.push((translation, Vec::new()));
+                    groups.len() - 1
+                });
+            let origins = &mut groups[i].1;
+            if !origins.iter().any(|o| o.headword == parsed.headword) {
+                origins.push(Origin {
+                    headword: parsed.headword.clone(),
+                    part_of_speech: parsed.part_of_speech.clone(),
+                });
+            }
+        }
+    }
+    groups
+}
+
+/// the words a definition translates its headword to
+///
+/// notes, labels, comments in brackets, grammar markers and
+/// cross-references are left out, e.g. `(building) Haus {n}; Heim,
+/// see {Heimat}` gives "Haus" and "Heim"
+fn translations(entry: &ParsedEntry) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for line in entry.senses.iter().flat_map(|sense| &sense.lines) {
+        if is_labelled(line) {
+            continue;
+        }
+        for term in without_brackets(line).split([',', ';']) {
+            let term = term.trim().trim_end_matches(['.', ':']).trim();
+            let words = term.split_whitespace().count();
+            let first = term.split_whitespace().next().unwrap_or_default();
+            if words == 0 || words > MAX_WORDS || matches!(first, "see" | "cf." | "e.g.") {
+                continue;
+            }
+            if !found.contains(&term.to_string()) {
+                found.push(term.to_string());
+            }
+        }
+    }
+    found
+}
+
+/// `Note: ...`, `Synonym: {home}` and similar lines
+fn is_labelled(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(label, _)| {
+        label.starts_with(char::is_uppercase) && label.split_whitespace().count() <= 2
+    })
+}
+
+/// `line` without anything in (), [], <> or {}
+fn without_brackets(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut depth = 0usize;
+    for c in line.chars() {
+        match c {
+            '(' | '[' | '<' | '{' => depth += 1,
+            ')' | ']' | '>' | '}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// a sense per original headword, written as a `{link}` back to it
+fn entry_text(translation: &str, origins: &[Origin]) -> String {
+    let shared_pos = origins
+        .iter()
+        .all(|o| o.part_of_speech == origins[0].part_of_speech)
+        .then(|| origins[0].part_of_speech.clone())
+        .flatten();
+
+    let numbered = origins.len() > 1;
+    let senses = origins
+        .iter()
+        .enumerate()
+        .map(|(i, origin)| {
+            let line = match &origin.part_of_speech {
+                Some(pos) if shared_pos.is_none() => format!("<{}> {{{}}}", pos, origin.headword),
+                _ => format!("{{{}}}", origin.headword),
+            };
+            Sense {
+                number: numbered.then_some(i + 1),
+                lines: vec![line],
+                examples: Vec::new(),
+            }
+        })
+        .collect();
+
+    ParsedEntry {
+        headword: translation.to_string(),
+        part_of_speech: shared_pos,
+        senses,
+        ..ParsedEntry::default()
+    }
+    .to_definition()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::{Cancel, DictionaryInfo, Hit, Hits};
+
+    fn entry(headword: &str, definition: &str) -> DictEntry {
+        DictEntry {
+            headword: headword.to_string(),
+            definition: definition.to_string(),
+            lemma: None,
+        }
+    }
+
+    #[test]
+    fn test_extracts_translations() {
+        let parsed = entry(
+            "house",
+            "house /haʊs/ <n>\n\
+             1. (building) Haus {n}; Heim, see {home}\n\
+             2. Haus {n}, a place where a family lives together\n\
+             Synonym: {home}\n\
+             Note: informal",
+        )
+        .parse();
+        assert_eq!(translations(&parsed), vec!["Haus", "Heim"]);
+    }
+
+    struct Entries(Vec<DictEntry>);
+
+    impl DictionarySource for Entries {
+        fn lookup(&self, _query: &str, _cancel: &Cancel) -> Hits {
+            Box::new(std::iter::empty())
+        }
+
+        fn entry(&self, _hit: &Hit) -> Option<DictEntry> {
+            None
+        }
+
+        fn entries(&self) -> Option<Box<dyn Iterator<Item = DictEntry> + '_>> {
+            Some(Box::new(self.0.iter().cloned()))
+        }
+
+        fn info(&self) -> Result<DictionaryInfo> {
+            Ok(DictionaryInfo::default())
+        }
+
+        fn entry_count(&self) -> usize {
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn test_reverse_entries_link_back() {
+        let entries = vec![
+            entry("house", "house <n>\nHaus {n}; Heim"),
+            entry("home", "home <n>\nHeim, Zuhause"),
+            entry("to live", "to live <v>\nwohnen, leben"),
+            entry("housing", "housing\nGehäuse, heim"),
+        ];
+        let groups = group_translations(Entries(entries).entries().unwrap());
+        let headwords: Vec<&str> = groups.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(
+            headwords,
+            vec!["Haus", "Heim", "Zuhause", "wohnen", "leben", "Gehäuse"]
+        );
+
+        let (translation, origins) = &groups[1];
+        let reversed = entry(translation, &entry_text(translation, origins)).parse();
+        assert_eq!(reversed.part_of_speech, None);
+        assert_eq!(reversed.links, vec!["house", "home", "housing"]);
+        assert_eq!(reversed.senses[0].lines, vec!["<n> {house}"]);
+        assert_eq!(reversed.senses[2].lines, vec!["{housing}"]);
+
+        let (translation, origins) = &groups[3];
+        let reversed = entry(translation, &entry_text(translation, origins)).parse();
+        assert_eq!(reversed.part_of_speech.as_deref(), Some("v"));
+        assert_eq!(reversed.links, vec!["to live"]);
+    }
+}
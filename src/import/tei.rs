@@ -0,0 +1,408 @@
+use crate::dictionary::{DictdWriter, ParsedEntry, Sense};
+use anyhow::{Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// children of a `gramGrp`
+const GRAMMAR: [&str; 10] = [
+    "pos", "gen", "num", "case", "per", "tns", "mood", "subc", "iType", "gram",
+];
+
+/// metadata from the `teiHeader`
+#[derive(Debug, Default, PartialEq)]
+struct TeiHeader {
+    title: Option<String>,
+    license: Vec<String>,
+    url: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct EntryBuilder {
+    headwords: Vec<String>,
+    pronunciation: Option<String>,
+    grammar: Vec<String>,
+    senses: Vec<SenseBuilder>,
+}
+
+#[derive(Debug, Default)]
+struct SenseBuilder {
+    translations: Vec<String>,
+    lines: Vec<String>,
+    examples: Vec<String>,
+}
+
+/// a `cit`, translations of an example end up in its parent
+#[derive(Debug, Default)]
+struct Cit {
+    kind: String,
+    quotes: Vec<String>,
+    grammar: Vec<String>,
+    translations: Vec<String>,
+}
+
+/// the entries of a TEI P5 dictionary, its number of entries and title
+pub(super) fn read_tei(path: &Path) -> Result<(DictdWriter, usize, Option<String>)> {
+    let file = File::open(path).with_context(|| format!("Failed to open TEI file: {:?}", path))?;
+
+    let mut writer = DictdWriter::new();
+    let mut count = 0;
+    let header = parse_tei(BufReader::new(file), |headwords, entry| {
+        writer.add(&headwords[0], &entry.to_definition());
+        for alias in &headwords[1..] {
+            writer.add_alias(alias);
+        }
+        count += 1;
+    })?;
+
+    if !header.license.is_empty() {
+        writer.add_metadata("license", &header.license.join("\n"));
+    }
+    if let Some(url) = &header.url {
+        writer.add_metadata("url", url);
+    }
+    Ok((writer, count, header.title))
+}
+
+/// streams through the document, `add` gets the headwords of every entry
+/// and the entry in the structure `parser::parse` would produce
+fn parse_tei(
+    input: impl BufRead,
+    mut add: impl FnMut(&[String], ParsedEntry),
+) -> Result<TeiHeader> {
+    let mut reader = Reader::from_reader(input);
+    let mut buf = Vec::new();
+
+    let mut header = TeiHeader::default();
+    // open elements and the text inside each of them
+    let mut stack: Vec<(String, String)> = Vec::new();
+    let mut entry: Option<EntryBuilder> = None;
+    let mut cits: Vec<Cit> = Vec::new();
+    let mut xr: Option<(String, Vec<String>)> = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .with_context(|| format!("Invalid TEI near byte {}", reader.buffer_position()))?;
+        match event {
+            Event::Start(e) => {
+                let name = local_name(&e);
+                match name.as_str() {
+                    "entry" => entry = Some(EntryBuilder::default()),
+                    "sense" => {
+                        if let Some(entry) = &mut entry {
+                            entry.senses.push(SenseBuilder::default());
+                        }
+                    }
+                    "cit" => cits.push(Cit {
+                        kind: attribute(&e, "type").unwrap_or_default(),
+                        ..Cit::default()
+                    }),
+                    "xr" => xr = Some((attribute(&e, "type").unwrap_or_default(), Vec::new())),
+                    _ => {}
+                }
+                stack.push((name, String::new()));
+            }
+            Event::Empty(e) => {
+                let in_publication = stack.iter().any(|(n, _)| n == "publicationStmt");
+                if local_name(&e) == "ptr" && in_publication && header.url.is_none() {
+                    header.url = attribute(&e, "target");
+                }
+            }
+            Event::Text(e) if keeps_text(&stack, entry.is_some()) => {
+                if let Some((_, text)) = stack.last_mut() {
+                    text.push_str(&e.unescape()?);
+                }
+            }
+            Event::CData(e) if keeps_text(&stack, entry.is_some()) => {
+                if let Some((_, text)) = stack.last_mut() {
+                    text.push_str(&String::from_utf8_lossy(&e));
+                }
+            }
+            Event::End(_) => {
+                let Some((name, raw)) = stack.pop() else {
+                    continue;
+                };
+                // mixed content like `<def>a <hi>big</hi> house</def>`, the
+                // elements around the entries don't keep theirs
+                if name != "entry" && keeps_text(&stack, entry.is_some()) {
+                    if let Some((_, parent_text)) = stack.last_mut() {
+                        parent_text.push_str(&raw);
+                    }
+                }
+                let value = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+                let parent = stack.last().map_or("", |(n, _)| n.as_str());
+                let inside = |element: &str| stack.iter().any(|(n, _)| n == element);
+
+                if inside("teiHeader") {
+                    match name.as_str() {
+                        "title" if parent == "titleStmt" && header.title.is_none() => {
+                            header.title = Some(value);
+                        }
+                        "p" | "licence" if inside("availability") && !value.is_empty() => {
+                            header.license.push(value);
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                let Some(current) = &mut entry else {
+                    continue;
+                };
+                match name.as_str() {
+                    "entry" => {
+                        let finished = std::mem::take(current);
+                        entry = None;
+                        if let Some(parsed) = finished.build() {
+                            add(&finished.headwords, parsed);
+                        }
+                    }
+                    _ if value.is_empty() && name != "cit" && name != "xr" => {}
+                    "orth" if cits.is_empty() => current.headwords.push(value),
+                    "pron" if cits.is_empty() && current.pronunciation.is_none() => {
+                        current.pronunciation = Some(value);
+                    }
+                    grammar if GRAMMAR.contains(&grammar) && parent == "gramGrp" => {
+                        let values = match cits.last_mut() {
+                            Some(cit) => &mut cit.grammar,
+                            None => &mut current.grammar,
+                        };
+                        if !values.contains(&value) {
+                            values.push(value);
+                        }
+                    }
+                    "quote" => {
+                        if let Some(cit) = cits.last_mut() {
+                            cit.quotes.push(value);
+                        }
+                    }
+                    "ref" if xr.is_some() => {
+                        if let Some((_, targets)) = &mut xr {
+                            targets.push(value);
+                        }
+                    }
+                    "def" | "note" if cits.is_empty() => current.sense().lines.push(value),
+                    "usg" if cits.is_empty() => current.sense().lines.push(format!("({})", value)),
+                    "cit" => {
+                        let Some(cit) = cits.pop() else {
+                            continue;
+                        };
+                        let quote = cit.quotes.join(", ");
+                        if quote.is_empty() {
+                            continue;
+                        }
+                        if cit.kind == "example" || cit.kind == "colloc" {
+                            let mut line = format!("\"{}\"", quote);
+                            if !cit.translations.is_empty() {
+                                line.push_str(&format!(" - {}", cit.translations.join("; ")));
+                            }
+                            current.sense().examples.push(line);
+                        } else {
+                            let translation = format!("{}{}", quote, grammar_marker(&cit.grammar));
+                            match cits.last_mut() {
+                                Some(parent) => parent.translations.push(translation),
+                                None => current.sense().translations.push(translation),
+                            }
+                        }
+                    }
+                    "xr" => {
+                        let Some((kind, targets)) = xr.take().filter(|(_, t)| !t.is_empty()) else {
+                            continue;
+                        };
+                        let label = match kind.as_str() {
+                            "syn" => "Synonym",
+                            "ant" => "Antonym",
+                            _ => "See also",
+                        };
+                        let links: Vec<String> =
+                            targets.iter().map(|t| format!("{{{}}}", t)).collect();
+                        current
+                            .sense()
+                            .lines
+                            .push(format!("{}: {}", label, links.join(", ")));
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(header)
+}
+
+impl EntryBuilder {
+    /// the sense the following content belongs to
+    fn sense(&mut self) -> &mut SenseBuilder {
+        if self.senses.is_empty() {
+            self.senses.push(SenseBuilder::default());
+        }
+        self.senses.last_mut().unwrap()
+    }
+
+    fn build(&self) -> Option<ParsedEntry> {
+        let headword = self.headwords.first()?;
+        let senses: Vec<&SenseBuilder> = self
+            .senses
+            .iter()
+            .filter(|s| !s.translations.is_empty() || !s.lines.is_empty())
+            .collect();
+        if senses.is_empty() {
+            return None;
+        }
+
+        let numbered = senses.len() > 1;
+        Some(ParsedEntry {
+            headword: headword.clone(),
+            pronunciation: self.pronunciation.clone(),
+            part_of_speech: (!self.grammar.is_empty()).then(|| self.grammar.join(", ")),
+            senses: senses
+                .into_iter()
+                .enumerate()
+                .map(|(i, sense)| {
+                    let translations =
+                        (!sense.translations.is_empty()).then(|| sense.translations.join(", "));
+                    Sense {
+                        number: numbered.then_some(i + 1),
+                        lines: translations
+                            .into_iter()
+                            .chain(sense.lines.iter().cloned())
+                            .collect(),
+                        examples: sense.examples.clone(),
+                    }
+                })
+                .collect(),
+            links: Vec::new(),
+        })
+    }
+}
+
+/// text is only read inside entries and the header, so the document
+/// isn't collected in the buffers of `<TEI>`, `<text>` and `<body>`
+fn keeps_text(stack: &[(String, String)], in_entry: bool) -> bool {
+    in_entry || stack.iter().any(|(name, _)| name == "teiHeader")
+}
+
+/// gender and number of a translation, e.g. ` {f}` like in the dictd releases
+fn grammar_marker(grammar: &[String]) -> String {
+    if grammar.is_empty() {
+        return String::new();
+    }
+    let short: Vec<&str> = grammar
+        .iter()
+        .map(|value| match value.as_str() {
+            "masc" | "m" => "m",
+            "fem" | "f" => "f",
+            "neut" | "n" => "n",
+            "pl" | "plural" => "pl",
+            "sg" | "singular" => "sg",
+            other => other,
+        })
+        .collect();
+    if short
+        .iter()
+        .all(|v| matches!(*v, "m" | "f" | "n" | "pl" | "sg"))
+    {
+        format!(" {{{}}}", short.join(", "))
+    } else {
+        format!(" ({})", grammar.join(", "))
+    }
+}
+
+fn local_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.local_name().as_ref()).into_owned()
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|a| a.key.local_name().as_ref() == name.as_bytes())
+        .and_then(|a| a.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<TEI xmlns="http://www.tei-c.org/ns/1.0">
+  <teiHeader>
+    <fileDesc>
+      <titleStmt><title>English-German FreeDict Dictionary</title></titleStmt>
+      <publicationStmt>
+        <availability><p>GNU General Public License</p></availability>
+        <ptr target="https://freedict.org/"/>
+      </publicationStmt>
+    </fileDesc>
+  </teiHeader>
+  <text><body>
+    <entry xml:id="house">
+      <form><orth>house</orth><orth>houses</orth><pron>haʊs</pron></form>
+      <gramGrp><pos>n</pos></gramGrp>
+      <sense n="1">
+        <cit type="trans"><quote>Haus</quote><gramGrp><gen>neut</gen></gramGrp></cit>
+        <cit type="trans"><quote>Gebäude</quote></cit>
+        <cit type="example">
+          <quote>The house is &quot;old&quot;.</quote>
+          <cit type="trans"><quote>Das Haus ist alt.</quote></cit>
+        </cit>
+      </sense>
+      <sense n="2">
+        <def>a <hi>family</hi> living together</def>
+        <xr type="syn"><ref target="#home">home</ref></xr>
+      </sense>
+    </entry>
+    <entry><form><orth>empty</orth></form></entry>
+  </body></text>
+</TEI>"##;
+
+    #[test]
+    fn test_parses_entries_and_header() {
+        let mut entries = Vec::new();
+        let header = parse_tei(SAMPLE.as_bytes(), |headwords, entry| {
+            entries.push((headwords.to_vec(), entry))
+        })
+        .unwrap();
+
+        assert_eq!(
+            header,
+            TeiHeader {
+                title: Some("English-German FreeDict Dictionary".to_string()),
+                license: vec!["GNU General Public License".to_string()],
+                url: Some("https://freedict.org/".to_string()),
+            }
+        );
+
+        assert_eq!(entries.len(), 1);
+        let (headwords, entry) = &entries[0];
+        assert_eq!(headwords, &["house", "houses"]);
+        assert_eq!(entry.pronunciation.as_deref(), Some("haʊs"));
+        assert_eq!(entry.part_of_speech.as_deref(), Some("n"));
+        assert_eq!(entry.senses[0].lines, vec!["Haus {n}, Gebäude"]);
+        assert_eq!(
+            entry.senses[0].examples,
+            vec!["\"The house is \"old\".\" - Das Haus ist alt."]
+        );
+        assert_eq!(
+            entry.senses[1].lines,
+            vec!["a family living together", "Synonym: {home}"]
+        );
+
+        // the dictd text reads back into the same structure, links included
+        let reparsed = crate::dictionary::DictEntry {
+            headword: "house".to_string(),
+            definition: entry.to_definition(),
+            lemma: None,
+        }
+        .parse();
+        assert_eq!(reparsed.senses, entry.senses);
+        assert_eq!(reparsed.links, vec!["home".to_string()]);
+    }
+}
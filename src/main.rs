@@ -3,19 +3,39 @@ mod components;
 mod config;
 mod dictionary;
 mod download;
+mod import;
 mod ui;
 
+use anyhow::{anyhow, bail, Context};
 use app::App;
+use config::Config;
 use crossterm::{
     event::poll,
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use dictionary::CacheManager;
+use import::{import_file, import_id, parse_columns, parse_delimiter, ImportOptions};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
 
 fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--import") {
+        return import(&args[i + 1..]);
+    }
+    if args.iter().any(|arg| arg == "--clear-cache") {
+        let removed = CacheManager::clear_all()?;
+        println!(
+            "Removed {} cache file(s) from {}",
+            removed,
+            CacheManager::cache_dir().display()
+        );
+        return Ok(());
+    }
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
@@ -27,12 +47,15 @@ fn main() -> anyhow::Result<()> {
     while !app.should_exit() {
         terminal.draw(|f| ui::draw(f, app.state()))?;
 
-        // poll with a timeout to update download progress
-        if poll(Duration::from_millis(100))? {
+        // poll with a timeout to update download progress and show search results
+        if poll(Duration::from_millis(50))? {
             app.handle_event()?;
         } else {
-            // no event, just check for download updates
+            // no event, just check for download, loading and search updates
             app.state_mut().check_download_progress();
+            app.state_mut().check_dictionary_loads();
+            app.state_mut().check_builds();
+            app.state_mut().check_search_results();
         }
     }
 
@@ -42,3 +65,49 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// `--import <file> [--name N] [--from L] [--to L] [--delimiter D] [--columns C] [--[no-]header] [--replace]`
+fn import(args: &[String]) -> anyhow::Result<()> {
+    let mut path = None;
+    let mut options = ImportOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| anyhow!("Missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--name" => options.name = Some(value()?),
+            "--from" => options.from_lang = Some(value()?.to_uppercase()),
+            "--to" => options.to_lang = Some(value()?.to_uppercase()),
+            "--delimiter" => options.glossary.delimiter = Some(parse_delimiter(&value()?)?),
+            "--columns" => options.glossary.columns = Some(parse_columns(&value()?)?),
+            "--header" => options.glossary.has_header = Some(true),
+            "--no-header" => options.glossary.has_header = Some(false),
+            "--replace" => options.replace = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(PathBuf::from(arg)),
+            _ => return Err(anyhow!("Unexpected argument {:?}", arg)),
+        }
+    }
+    let path = path.context("Usage: tuidict --import <file.tei|file.tsv|file.csv> [options]")?;
+
+    let data_dir = Config::data_dir()?;
+    if !options.replace && data_dir.join(import_id(&path, &options)).exists() {
+        bail!(
+            "{} was already imported, pass --replace to import it again",
+            path.display()
+        );
+    }
+    let (dict_config, count) = import_file(&path, &options, &data_dir)?;
+    let mut config = Config::load()?;
+    println!(
+        "Imported {} entries from {} as {:?}",
+        count,
+        path.display(),
+        dict_config.name
+    );
+    config.add_dictionary(dict_config);
+    config.save()
+}
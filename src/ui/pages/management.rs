@@ -1,10 +1,10 @@
-use crate::app::AppState;
+use crate::app::{AppState, DictStatus};
 use crate::components::{StatusBar, StatusType};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::Line,
-    widgets::{Block, Borders, List, ListItem, ListState},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
@@ -14,12 +14,14 @@ pub fn render(f: &mut Frame, state: &AppState) {
         .constraints([Constraint::Min(1), Constraint::Length(3)])
         .split(f.size());
 
-    render_management_list(f, state, chunks[0]);
-    render_footer(
-        f,
-        chunks[1],
-        "1: Translation | 2: [Manage] | 3: Download | Space/Enter: Toggle | d: Delete | q: Quit",
-    );
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[0]);
+
+    render_management_list(f, state, columns[0]);
+    render_info_panel(f, state, columns[1]);
+    render_footer(f, state, chunks[1]);
 }
 
 fn render_management_list(f: &mut Frame, state: &AppState, area: Rect) {
@@ -29,9 +31,17 @@ fn render_management_list(f: &mut Frame, state: &AppState, area: Rect) {
         .iter()
         .map(|dict| {
             let status = if dict.active { "[✓]" } else { "[ ]" };
+            let load_status = match state.dict_status.get(&dict.id) {
+                Some(DictStatus::Loading) => " - loading...".to_string(),
+                Some(DictStatus::Failed(e)) => format!(" - failed: {}", e),
+                None => match state.loaded_dictionaries.get(&dict.id) {
+                    Some(source) => format!(" - {} entries", source.entry_count()),
+                    None => String::new(),
+                },
+            };
             let text = format!(
-                "{} {} ({} -> {})",
-                status, dict.name, dict.from_lang, dict.to_lang
+                "{} {} ({} -> {}){}",
+                status, dict.name, dict.from_lang, dict.to_lang, load_status
             );
             ListItem::new(Line::from(text))
         })
@@ -57,7 +67,91 @@ fn render_management_list(f: &mut Frame, state: &AppState, area: Rect) {
     f.render_stateful_widget(list, area, &mut list_state);
 }
 
-fn render_footer(f: &mut Frame, area: Rect, help_text: &str) {
-    let status_bar = StatusBar::new(help_text, StatusType::Help).show_border(false);
-    status_bar.render(f, area);
+/// `00-database-*` metadata of the selected dictionary
+fn render_info_panel(f: &mut Frame, state: &AppState, area: Rect) {
+    let label = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let field = |name: String, value: &str| -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(Span::styled(name, label))];
+        lines.extend(value.lines().map(|line| Line::from(format!("  {}", line))));
+        lines
+    };
+
+    let selected = state.config.dictionaries.get(state.management_selected);
+    let lines: Vec<Line> = match selected.and_then(|dict| state.dict_info.get(&dict.id)) {
+        None => Vec::new(),
+        Some(Err(e)) => vec![Line::from(format!("Failed to read dictionary info: {}", e))],
+        Some(Ok(info)) if info.is_empty() => {
+            vec![Line::from("This dictionary has no metadata entries.")]
+        }
+        Some(Ok(info)) => {
+            let mut lines = Vec::new();
+            for (name, value) in [
+                ("Title", &info.title),
+                ("URL", &info.url),
+                ("License", &info.license),
+                ("Description", &info.description),
+            ] {
+                if let Some(value) = value {
+                    lines.extend(field(name.to_string(), value));
+                }
+            }
+
+            let flags: Vec<&str> = [
+                (info.utf8, "utf-8"),
+                (info.allchars, "all characters"),
+                (info.case_sensitive, "case sensitive"),
+            ]
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, flag)| *flag)
+            .collect();
+            if !flags.is_empty() {
+                lines.extend(field("Flags".to_string(), &flags.join(", ")));
+            }
+
+            for (name, value) in &info.other {
+                lines.extend(field(name.clone(), value));
+            }
+            lines
+        }
+    };
+
+    let panel = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(" Info "))
+        .wrap(Wrap { trim: false });
+    f.render_widget(panel, area);
+}
+
+fn render_footer(f: &mut Frame, state: &AppState, area: Rect) {
+    let import_prompt = state.import_input.as_ref().map(|input| {
+        format!(
+            "Import TEI or TSV/CSV file: {}_\nEnter: Import | Esc: Cancel",
+            input
+        )
+    });
+
+    let import_prompt = import_prompt.or_else(|| {
+        let path = state.import_confirm.as_ref()?;
+        Some(format!(
+            "{} was imported before, replace it?\ny: Replace | any other key: Cancel",
+            path.display()
+        ))
+    });
+
+    let building = state.building.as_ref().map(|label| format!("{}...", label));
+
+    let status_bar = match (&import_prompt, &state.notice) {
+        (Some(prompt), _) => StatusBar::new(prompt, StatusType::Info),
+        (None, Some(notice)) => StatusBar::new(notice, StatusType::Info),
+        (None, None) if building.is_some() => {
+            StatusBar::new(building.as_deref().unwrap_or_default(), StatusType::Loading)
+        }
+        (None, None) => StatusBar::new(
+            "1: Translation | 2: [Manage] | 3: Download | Space/Enter: Toggle | d: Delete | c: Clear Cache | i: Import | r: Reverse | q: Quit",
+            StatusType::Help,
+        ),
+    };
+    status_bar.show_border(false).render(f, area);
 }
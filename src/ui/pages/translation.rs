@@ -1,9 +1,10 @@
-use crate::app::{AppState, InputMode};
+use crate::app::{AppState, DictStatus, InputMode, SearchMode};
 use crate::components::{SearchInput, StatusBar, StatusType};
+use crate::dictionary::{segments, ParsedEntry, Segment};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::Line,
+    text::{Line, Span, Text},
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
@@ -20,16 +21,18 @@ pub fn render(f: &mut Frame, state: &AppState) {
 
     render_search_bar(f, state, chunks[0]);
     render_main_area(f, state, chunks[1]);
-    render_footer(
-        f,
-        chunks[2],
-        "1: [Translation] | 2: Manage | 3: Download | Tab: Switch Dict | q: Quit",
-    );
+    render_footer(f, state, chunks[2]);
 }
 
 fn render_search_bar(f: &mut Frame, state: &AppState, area: Rect) {
     let dict_name = state.get_active_dict_name();
-    let title = format!("Search ({})", dict_name);
+    let mut title = match state.search_mode {
+        SearchMode::Prefix => format!("Search ({})", dict_name),
+        mode => format!("Search ({}) [{}]", dict_name, mode.label()),
+    };
+    if let Some(frame) = state.search_spinner() {
+        title.push_str(&format!(" {} searching...", frame));
+    }
     let search_input = SearchInput::new(&state.input)
         .title(&title)
         .show_cursor(state.input_mode == InputMode::Editing)
@@ -46,7 +49,24 @@ fn render_main_area(f: &mut Frame, state: &AppState, area: Rect) {
     let items: Vec<ListItem> = state
         .results
         .iter()
-        .map(|entry| ListItem::new(Line::from(entry.headword.clone())))
+        .map(|item| {
+            let mut spans = vec![Span::raw(item.hit.headword.clone())];
+            if item.hit.lemma.is_some() {
+                spans.push(Span::styled(
+                    "  via lemma",
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::ITALIC),
+                ));
+            }
+            if let Some(source) = &item.source {
+                spans.push(Span::styled(
+                    format!("  [{}]", source),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            ListItem::new(Line::from(spans))
+        })
         .collect();
 
     let highlight_style = Style::default()
@@ -54,12 +74,22 @@ fn render_main_area(f: &mut Frame, state: &AppState, area: Rect) {
         .fg(Color::White)
         .add_modifier(Modifier::BOLD);
 
+    let more = if state.has_more_results() { "+" } else { "" };
+    let mut results_title = format!(" Results ({}{}", state.results.len(), more);
+    if state.fuzzy_fallback {
+        results_title.push_str(", fuzzy");
+    }
+    if state.search_all && state.group_results {
+        results_title.push_str(", grouped");
+    }
+    results_title.push_str(") ");
+
     let list = List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_style(Style::default().fg(Color::Green))
-                .title(format!(" Results ({}) ", state.results.len())),
+                .title(results_title),
         )
         .highlight_style(highlight_style);
 
@@ -67,23 +97,175 @@ fn render_main_area(f: &mut Frame, state: &AppState, area: Rect) {
     list_state.select(Some(state.selected_index));
     f.render_stateful_widget(list, chunks[0], &mut list_state);
 
-    let definition_text = if state.loaded_dictionaries.is_empty() {
-        "No active dictionaries. Press '3' to download or '2' to manage.".to_string()
-    } else if let Some(entry) = state.results.get(state.selected_index) {
-        entry.definition.clone()
-    } else if state.input.is_empty() {
-        "Start typing to search...".to_string()
-    } else {
-        "No results found.".to_string()
-    };
+    let parsed = state.selected_entry.as_ref().map(|e| e.parse());
+    let selected_link = parsed
+        .as_ref()
+        .zip(state.selected_link)
+        .and_then(|(entry, i)| entry.links.get(i))
+        .map(String::as_str);
 
+    let definition_text: Text =
+        if state.loaded_dictionaries.is_empty() && !state.dict_status.is_empty() {
+            loading_text(state).into()
+        } else if state.loaded_dictionaries.is_empty() {
+            "No active dictionaries. Press '3' to download or '2' to manage.".into()
+        } else if let Some(entry) = &parsed {
+            entry_text(entry, selected_link)
+        } else if let Some(error) = &state.search_error {
+            error.clone().into()
+        } else if state.input.is_empty() {
+            "Start typing to search...".into()
+        } else {
+            "No results found.".into()
+        };
+
+    // trimming would drop the indentation of continuation lines
     let definition = Paragraph::new(definition_text)
-        .block(Block::default().borders(Borders::ALL).title(" Definition "))
-        .wrap(Wrap { trim: true });
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(definition_title(state, parsed.as_ref(), selected_link)),
+        )
+        .wrap(Wrap { trim: false });
     f.render_widget(definition, chunks[1]);
 }
 
-fn render_footer(f: &mut Frame, area: Rect, help_text: &str) {
-    let status_bar = StatusBar::new(help_text, StatusType::Help).show_border(false);
-    status_bar.render(f, area);
+/// position of the selected link and the depth of the history
+fn definition_title(
+    state: &AppState,
+    entry: Option<&ParsedEntry>,
+    selected_link: Option<&str>,
+) -> String {
+    let mut title = " Definition ".to_string();
+    if let Some(lemma) = state.selected_entry.as_ref().and_then(|e| e.lemma.as_ref()) {
+        title.push_str(&format!("- {} via lemma: {} ", state.input.trim(), lemma));
+    }
+    let link_count = entry.map_or(0, |e| e.links.len());
+    if let Some(link) = selected_link {
+        let position = state.selected_link.unwrap_or(0) + 1;
+        title.push_str(&format!("- link {}/{}: {} ", position, link_count, link));
+    } else if link_count > 0 {
+        title.push_str(&format!("- {} link(s) ", link_count));
+    }
+    if !state.back_history.is_empty() {
+        title.push_str(&format!("[back: {}] ", state.back_history.len()));
+    }
+    title
+}
+
+/// headword line, then every sense with its examples indented below it
+fn entry_text(entry: &ParsedEntry, selected_link: Option<&str>) -> Text<'static> {
+    let mut header = vec![Span::styled(
+        entry.headword.clone(),
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    if let Some(pronunciation) = &entry.pronunciation {
+        header.push(Span::styled(
+            format!("  /{}/", pronunciation),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    if let Some(pos) = &entry.part_of_speech {
+        header.push(Span::styled(
+            format!("  {}", pos),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::ITALIC),
+        ));
+    }
+
+    let mut lines = vec![Line::from(header), Line::default()];
+    for sense in &entry.senses {
+        let marker = match sense.number {
+            Some(number) => format!("{:>2}. ", number),
+            None => String::new(),
+        };
+        let indent = " ".repeat(marker.chars().count());
+
+        for (i, text) in sense.lines.iter().enumerate() {
+            let prefix = if i == 0 {
+                marker.clone()
+            } else {
+                indent.clone()
+            };
+            let mut spans = vec![Span::styled(
+                prefix,
+                Style::default().add_modifier(Modifier::BOLD),
+            )];
+            spans.extend(link_spans(text, selected_link));
+            lines.push(Line::from(spans));
+        }
+        if sense.lines.is_empty() && !marker.is_empty() {
+            lines.push(Line::from(marker.clone()));
+        }
+
+        for example in &sense.examples {
+            let mut spans = vec![Span::raw(format!("{}  ", indent))];
+            spans.extend(link_spans(example, selected_link).into_iter().map(|span| {
+                // links keep their own color
+                let color = span.style.fg.unwrap_or(Color::DarkGray);
+                let style = span.style.fg(color).add_modifier(Modifier::ITALIC);
+                span.style(style)
+            }));
+            lines.push(Line::from(spans));
+        }
+    }
+
+    Text::from(lines)
+}
+
+/// `{cross-references}` are underlined, the selected one is highlighted
+fn link_spans(text: &str, selected_link: Option<&str>) -> Vec<Span<'static>> {
+    segments(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => Span::raw(text.to_string()),
+            Segment::Link(target) if Some(target) == selected_link => Span::styled(
+                target.to_string(),
+                Style::default()
+                    .bg(Color::Blue)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Segment::Link(target) => Span::styled(
+                target.to_string(),
+                Style::default()
+                    .fg(Color::Blue)
+                    .add_modifier(Modifier::UNDERLINED),
+            ),
+        })
+        .collect()
+}
+
+/// startup screen listing every dictionary that is not ready yet
+fn loading_text(state: &AppState) -> String {
+    state
+        .config
+        .dictionaries
+        .iter()
+        .filter_map(|dict| match state.dict_status.get(&dict.id)? {
+            DictStatus::Loading => Some(format!("Loading {}...", dict.name)),
+            DictStatus::Failed(e) => Some(format!("Failed to load {}: {}", dict.name, e)),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_footer(f: &mut Frame, state: &AppState, area: Rect) {
+    let loading = state.loading_dictionaries();
+    let loading_message = format!("Loading {}...", loading.join(", "));
+
+    let status_bar = match &state.notice {
+        Some(notice) => StatusBar::new(notice, StatusType::Info),
+        None if !loading.is_empty() => StatusBar::new(&loading_message, StatusType::Loading),
+        None if state.input_mode == InputMode::Normal => StatusBar::new(
+            "j/k: Select | n/N: Next/Prev Link | Enter: Follow | h/l: Back/Forward | /: Search | q: Quit",
+            StatusType::Help,
+        ),
+        None => StatusBar::new(
+            "1: [Translation] | 2: Manage | 3: Download | Tab: Switch Dict | Ctrl+f: Fuzzy | Ctrl+s: Mode | Ctrl+g: Group | q: Quit",
+            StatusType::Help,
+        ),
+    };
+    status_bar.show_border(false).render(f, area);
 }